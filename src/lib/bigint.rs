@@ -0,0 +1,453 @@
+//! Arbitrary-precision integers.
+//!
+//! [`MikuType`] is [`Copy`] and fixed in size, so integers that don't fit into any of its
+//! variants are represented by [`BigInt`] instead. A [`BigInt`] stores its magnitude as a
+//! vector of 32 bit limbs on the heap and can be converted to and from the integer variants
+//! of [`MikuType`].
+//!
+//! Programs keep big integers in heap blocks of the vm, see [`crate::miku::MikuVM::alloc_bigint`],
+//! and work with them through pointers with the [`crate::inst::ToBig`], [`crate::inst::FromBig`],
+//! [`crate::inst::BigCmp`] and big integer arithmetic instructions.
+//!
+//! ## Examples
+//! ``` rust
+//! use vm::{bigint::BigInt, types::MikuType};
+//!
+//! let a = BigInt::try_from(MikuType::U128(u128::MAX)).unwrap();
+//! let b = &a * &a;
+//! assert_eq!("115792089237316195423570985008687907852589419931798687112530834793049593217025", b.to_string());
+//! assert_eq!(MikuType::U128(u128::MAX), (b / a).unwrap().to_miku_type(0x0C).unwrap());
+//! ```
+
+use crate::{error::{MikuError, ParseTypeError}, types::MikuType};
+use std::{cmp::Ordering, fmt::Display, ops::{Add, Div, Mul, Neg, Rem, Sub}, str::FromStr};
+
+/// An arbitrary-precision signed integer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigInt {
+    /// True if the value is less than zero. Zero is never negative.
+    negative: bool,
+    /// The magnitude in little endian limb order without trailing zero limbs.
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    /// Creates a zero valued [`BigInt`].
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    fn from_parts(negative: bool, mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        let negative = negative && !limbs.is_empty();
+        Self { negative, limbs }
+    }
+
+    /// Creates a value from its sign and its magnitude as 32 bit limbs, least significant first.
+    pub fn from_limbs(negative: bool, limbs: Vec<u32>) -> Self {
+        Self::from_parts(negative, limbs)
+    }
+
+    /// The magnitude as 32 bit limbs, least significant first, without trailing zero limbs.
+    pub fn limbs(&self) -> &[u32] {
+        &self.limbs
+    }
+
+    /// Returns true if the value is zero.
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    /// Returns true if the value is less than zero.
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// The absolute value.
+    pub fn abs(&self) -> BigInt {
+        Self::from_parts(false, self.limbs.clone())
+    }
+
+    /// Converts the value into the integer [`MikuType`] with the given type identifier.
+    /// # Returns
+    /// - `Ok(MikuType)` if the value fits into the type.
+    /// - [`MikuError::IntegerConversionError`] if the value doesn't fit into the type.
+    /// - [`MikuError::UnknownTypeError`] if the type identifier is not an integer type.
+    pub fn to_miku_type(&self, type_id: u8) -> Result<MikuType, MikuError> {
+        let overflow = || MikuError::IntegerConversionError(format!("{} TO TYPE {}", self, type_id));
+
+        match type_id {
+            0x00 => self.to_u128().and_then(|value| value.try_into().ok()).map(MikuType::U8).ok_or_else(overflow),
+            0x01 => self.to_u128().and_then(|value| value.try_into().ok()).map(MikuType::U16).ok_or_else(overflow),
+            0x02 => self.to_u128().and_then(|value| value.try_into().ok()).map(MikuType::U32).ok_or_else(overflow),
+            0x03 => self.to_u128().and_then(|value| value.try_into().ok()).map(MikuType::U64).ok_or_else(overflow),
+            0x0C => self.to_u128().map(MikuType::U128).ok_or_else(overflow),
+            0x04 => self.to_i128().and_then(|value| value.try_into().ok()).map(MikuType::I8).ok_or_else(overflow),
+            0x05 => self.to_i128().and_then(|value| value.try_into().ok()).map(MikuType::I16).ok_or_else(overflow),
+            0x06 => self.to_i128().and_then(|value| value.try_into().ok()).map(MikuType::I32).ok_or_else(overflow),
+            0x07 => self.to_i128().and_then(|value| value.try_into().ok()).map(MikuType::I64).ok_or_else(overflow),
+            0x0D => self.to_i128().map(MikuType::I128).ok_or_else(overflow),
+            _ => Err(MikuError::UnknownTypeError(type_id)),
+        }
+    }
+
+    /// The magnitude as a [`u128`] if the value is not negative and fits.
+    fn to_u128(&self) -> Option<u128> {
+        if self.negative || self.limbs.len() > 4 {
+            return None;
+        }
+
+        Some(self.limbs.iter().rev().fold(0u128, |acc, limb| (acc << 32) | *limb as u128))
+    }
+
+    /// The value as an [`i128`] if it fits.
+    fn to_i128(&self) -> Option<i128> {
+        let magnitude = self.abs().to_u128()?;
+
+        if self.negative {
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+
+    /// Truncated division of two values. The remainder has the sign of the dividend.
+    /// # Returns
+    /// - `Ok((quotient, remainder))`
+    /// - [`MikuError::DivisionByZeroError`] if the divisor is zero.
+    pub fn div_rem(&self, rhs: &BigInt) -> Result<(BigInt, BigInt), MikuError> {
+        if rhs.is_zero() {
+            return Err(MikuError::DivisionByZeroError);
+        }
+
+        let (quotient, remainder) = div_rem_magnitude(&self.limbs, &rhs.limbs);
+        Ok((
+            Self::from_parts(self.negative != rhs.negative, quotient),
+            Self::from_parts(self.negative, remainder),
+        ))
+    }
+}
+
+impl From<u128> for BigInt {
+    fn from(value: u128) -> Self {
+        let limbs = (0..4).map(|i| (value >> (32 * i)) as u32).collect();
+        Self::from_parts(false, limbs)
+    }
+}
+
+impl From<i128> for BigInt {
+    fn from(value: i128) -> Self {
+        let magnitude = BigInt::from(value.unsigned_abs());
+        Self::from_parts(value < 0, magnitude.limbs)
+    }
+}
+
+/// Takes an integer [MikuType] and turns it into a [BigInt].
+/// ### Results in
+/// - [`BigInt`]
+/// - [`MikuError::IntegerConversionError`] if the value is not an integer.
+impl TryFrom<MikuType> for BigInt {
+    type Error = MikuError;
+
+    fn try_from(value: MikuType) -> Result<Self, Self::Error> {
+        match value {
+            MikuType::U8(value) => Ok(BigInt::from(value as u128)),
+            MikuType::U16(value) => Ok(BigInt::from(value as u128)),
+            MikuType::U32(value) => Ok(BigInt::from(value as u128)),
+            MikuType::U64(value) => Ok(BigInt::from(value as u128)),
+            MikuType::U128(value) => Ok(BigInt::from(value)),
+            MikuType::I8(value) => Ok(BigInt::from(value as i128)),
+            MikuType::I16(value) => Ok(BigInt::from(value as i128)),
+            MikuType::I32(value) => Ok(BigInt::from(value as i128)),
+            MikuType::I64(value) => Ok(BigInt::from(value as i128)),
+            MikuType::I128(value) => Ok(BigInt::from(value)),
+            _ => Err(MikuError::IntegerConversionError(format!("{:?} TO BIGINT", value))),
+        }
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.limbs, &other.limbs),
+            (true, true) => cmp_magnitude(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> Self::Output {
+        Self::from_parts(!self.negative, self.limbs)
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        if self.negative == rhs.negative {
+            return BigInt::from_parts(self.negative, add_magnitude(&self.limbs, &rhs.limbs));
+        }
+
+        match cmp_magnitude(&self.limbs, &rhs.limbs) {
+            Ordering::Less => BigInt::from_parts(rhs.negative, sub_magnitude(&rhs.limbs, &self.limbs)),
+            _ => BigInt::from_parts(self.negative, sub_magnitude(&self.limbs, &rhs.limbs)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + &-rhs.clone()
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        BigInt::from_parts(self.negative != rhs.negative, mul_magnitude(&self.limbs, &rhs.limbs))
+    }
+}
+
+/// [Div] implementation for [BigInt].
+/// #### Results in
+/// - [`BigInt`] the quotient truncated towards zero.
+/// - [`MikuError::DivisionByZeroError`] is returned if the second parameter is 0.
+impl Div for &BigInt {
+    type Output = Result<BigInt, MikuError>;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.div_rem(rhs).map(|(quotient, _)| quotient)
+    }
+}
+
+/// [Rem] implementation for [BigInt].
+/// #### Results in
+/// - [`BigInt`] the remainder which has the sign of the first parameter.
+/// - [`MikuError::DivisionByZeroError`] is returned if the second parameter is 0.
+impl Rem for &BigInt {
+    type Output = Result<BigInt, MikuError>;
+
+    fn rem(self, rhs: Self) -> Self::Output {
+        self.div_rem(rhs).map(|(_, remainder)| remainder)
+    }
+}
+
+/// Used to implement the operator traits for owned [BigInt] values by delegating to the
+/// implementations for references.
+macro_rules! impl_owned_op {
+    ($operation: ident, $method: ident, $output: ty) => {
+        impl $operation for BigInt {
+            type Output = $output;
+
+            fn $method(self, rhs: Self) -> Self::Output {
+                (&self).$method(&rhs)
+            }
+        }
+    };
+}
+
+impl_owned_op!(Add, add, BigInt);
+impl_owned_op!(Sub, sub, BigInt);
+impl_owned_op!(Mul, mul, BigInt);
+impl_owned_op!(Div, div, Result<BigInt, MikuError>);
+impl_owned_op!(Rem, rem, Result<BigInt, MikuError>);
+
+/// Formats the value in decimal.
+impl Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        let mut chunks = Vec::new();
+        let mut magnitude = self.limbs.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = div_rem_small(&magnitude, DECIMAL_CHUNK);
+            chunks.push(remainder);
+            magnitude = quotient;
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", chunks.pop().unwrap_or(0))?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a decimal integer with an optional sign.
+impl FromStr for BigInt {
+    type Err = ParseTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseTypeError::InvalidLiteral("bigint".to_string(), s.to_string());
+
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        let mut limbs: Vec<u32> = Vec::new();
+        let first_chunk_length = match digits.len() % 9 {
+            0 => 9,
+            length => length,
+        };
+        let mut start = 0;
+        let mut end = first_chunk_length;
+        while start < digits.len() {
+            let chunk: u32 = digits[start..end].parse().map_err(|_| invalid())?;
+            limbs = mul_add_small(&limbs, DECIMAL_CHUNK, chunk);
+            start = end;
+            end += 9;
+        }
+
+        Ok(BigInt::from_parts(negative, limbs))
+    }
+}
+
+/// The largest power of ten that fits into a limb.
+const DECIMAL_CHUNK: u32 = 1_000_000_000;
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    result.push(carry as u32);
+
+    result
+}
+
+/// Subtracts the magnitude `b` from the magnitude `a`. `a` has to be at least `b`.
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+
+    for (i, limb) in a.iter().enumerate() {
+        let mut difference = *limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if difference < 0 {
+            difference += 1 << 32;
+            borrow = 1;
+        }
+        result.push(difference as u32);
+    }
+
+    result
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; a.len() + b.len()];
+
+    for (i, a_limb) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, b_limb) in b.iter().enumerate() {
+            let product = *a_limb as u64 * *b_limb as u64 + result[i + j] as u64 + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+
+    result
+}
+
+/// Computes `a * multiplier + addend`.
+fn mul_add_small(a: &[u32], multiplier: u32, addend: u32) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len() + 1);
+    let mut carry = addend as u64;
+
+    for limb in a {
+        let product = *limb as u64 * multiplier as u64 + carry;
+        result.push(product as u32);
+        carry = product >> 32;
+    }
+    if carry != 0 {
+        result.push(carry as u32);
+    }
+
+    result
+}
+
+/// Divides a magnitude by a single limb returning the quotient without trailing zero limbs
+/// and the remainder.
+fn div_rem_small(a: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder = 0u64;
+
+    for (i, limb) in a.iter().enumerate().rev() {
+        let current = (remainder << 32) | *limb as u64;
+        quotient[i] = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+    while quotient.last() == Some(&0) {
+        quotient.pop();
+    }
+
+    (quotient, remainder as u32)
+}
+
+/// Divides the magnitude `a` by the non zero magnitude `b` with binary long division.
+fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if b.len() == 1 {
+        let (quotient, remainder) = div_rem_small(a, b[0]);
+        return (quotient, vec![remainder]);
+    }
+
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = Vec::with_capacity(b.len() + 1);
+
+    for bit in (0..a.len() * 32).rev() {
+        // remainder = remainder << 1 | next bit of a
+        let mut carry = (a[bit / 32] >> (bit % 32)) & 1;
+        for limb in remainder.iter_mut() {
+            let next_carry = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next_carry;
+        }
+        if carry != 0 {
+            remainder.push(carry);
+        }
+
+        if cmp_magnitude(&remainder, b) != Ordering::Less {
+            remainder = sub_magnitude(&remainder, b);
+            while remainder.last() == Some(&0) {
+                remainder.pop();
+            }
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+
+    (quotient, remainder)
+}
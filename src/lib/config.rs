@@ -0,0 +1,203 @@
+//! Runtime configuration of the vm.
+//!
+//! A [`VmConfig`] describes how large the memory of a [`crate::miku::MikuVM`] is and how it is
+//! split into the stack, .data and heap segments. Configurations are created with
+//! [`VmConfigBuilder`] which validates the layout.
+//!
+//! ## Examples
+//! ``` rust
+//! use vm::{config::VmConfig, miku::MikuVM, types::Segment};
+//!
+//! let config = VmConfig::builder()
+//!     .memory_size(64)
+//!     .stack_size(16)
+//!     .data_size(8)
+//!     .build()
+//!     .unwrap();
+//! let vm = MikuVM::with_config(config);
+//! assert_eq!(24..64, vm.layout().segment(Segment::Heap));
+//! ```
+
+use std::ops::Range;
+
+use crate::{error::MikuError, types::Segment, DATA_END, DATA_START, HEAP_END, HEAP_START, MEMORY_SIZE, STACK_END, STACK_START};
+
+/// Where each memory segment starts and ends.
+/// The segments are laid out in the order stack, .data, heap without gaps between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryLayout {
+    stack: Range<usize>,
+    data: Range<usize>,
+    heap: Range<usize>,
+}
+
+impl MemoryLayout {
+    /// The total number of memory cells.
+    pub fn memory_size(&self) -> usize {
+        self.heap.end
+    }
+
+    /// The range of addresses that belong to the given segment.
+    /// The byte memory isn't made of cells, so its range is empty.
+    pub fn segment(&self, segment: Segment) -> Range<usize> {
+        match segment {
+            Segment::Stack => self.stack.clone(),
+            Segment::Data => self.data.clone(),
+            Segment::Heap => self.heap.clone(),
+            Segment::Bytes => 0..0,
+        }
+    }
+
+    /// Returns the segment the given address belongs to.
+    pub fn segment_of(&self, address: usize) -> Option<Segment> {
+        [Segment::Stack, Segment::Data, Segment::Heap]
+            .into_iter()
+            .find(|segment| self.segment(*segment).contains(&address))
+    }
+}
+
+impl Default for MemoryLayout {
+    fn default() -> Self {
+        Self {
+            stack: STACK_START..STACK_END,
+            data: DATA_START..DATA_END,
+            heap: HEAP_START..HEAP_END,
+        }
+    }
+}
+
+/// The configuration of a [`crate::miku::MikuVM`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VmConfig {
+    layout: MemoryLayout,
+    byte_memory_size: usize,
+    gc_threshold: Option<usize>,
+    gas_limit: Option<u64>,
+}
+
+impl VmConfig {
+    /// Creates a builder starting from the default configuration.
+    pub fn builder() -> VmConfigBuilder {
+        VmConfigBuilder::new()
+    }
+
+    /// The memory layout.
+    pub fn layout(&self) -> &MemoryLayout {
+        &self.layout
+    }
+
+    /// The size of the byte-addressed memory in bytes.
+    pub fn byte_memory_size(&self) -> usize {
+        self.byte_memory_size
+    }
+
+    /// The number of live heap cells above which allocations trigger a garbage collection.
+    /// `None` if the collector only runs when it is triggered explicitly.
+    pub fn gc_threshold(&self) -> Option<usize> {
+        self.gc_threshold
+    }
+
+    /// The gas budget of the vm. `None` if execution isn't metered.
+    pub fn gas_limit(&self) -> Option<u64> {
+        self.gas_limit
+    }
+}
+
+/// Builder for [`VmConfig`].
+///
+/// Unless set explicitly the stack and the .data segment each take 30% of the memory and the
+/// heap takes whatever is left.
+#[derive(Debug, Clone)]
+pub struct VmConfigBuilder {
+    memory_size: usize,
+    stack_size: Option<usize>,
+    data_size: Option<usize>,
+    byte_memory_size: usize,
+    gc_threshold: Option<usize>,
+    gas_limit: Option<u64>,
+}
+
+impl VmConfigBuilder {
+    pub fn new() -> Self {
+        Self { memory_size: MEMORY_SIZE, stack_size: None, data_size: None, byte_memory_size: 0, gc_threshold: None, gas_limit: None }
+    }
+
+    /// Sets the total number of memory cells.
+    pub fn memory_size(mut self, memory_size: usize) -> Self {
+        self.memory_size = memory_size;
+        self
+    }
+
+    /// Sets the number of cells of the stack segment.
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = Some(stack_size);
+        self
+    }
+
+    /// Sets the number of cells of the .data segment.
+    pub fn data_size(mut self, data_size: usize) -> Self {
+        self.data_size = Some(data_size);
+        self
+    }
+
+    /// Sets the size of the byte-addressed memory in bytes.
+    pub fn byte_memory_size(mut self, byte_memory_size: usize) -> Self {
+        self.byte_memory_size = byte_memory_size;
+        self
+    }
+
+    /// Enables automatic garbage collection. Allocations that would make more than the given
+    /// number of heap cells live, or that don't fit into the heap, collect garbage first.
+    pub fn gc_threshold(mut self, gc_threshold: usize) -> Self {
+        self.gc_threshold = Some(gc_threshold);
+        self
+    }
+
+    /// Enables gas metering with the given budget.
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = Some(gas_limit);
+        self
+    }
+
+    /// Validates the settings and creates the configuration.
+    ///
+    /// # Returns
+    /// - `Ok(VmConfig)` if the configuration is valid.
+    /// - [`MikuError::InvalidConfig`] if the memory or the stack is empty or the stack and the
+    ///   .data segment don't fit into the memory.
+    pub fn build(self) -> Result<VmConfig, MikuError> {
+        let stack_size = self.stack_size.unwrap_or_else(|| default_segment_size(self.memory_size));
+        let data_size = self.data_size.unwrap_or_else(|| default_segment_size(self.memory_size));
+
+        if self.memory_size == 0 {
+            return Err(MikuError::InvalidConfig("MEMORY SIZE IS 0".to_string()));
+        }
+        if stack_size == 0 {
+            return Err(MikuError::InvalidConfig("STACK SIZE IS 0".to_string()));
+        }
+        if stack_size.checked_add(data_size).is_none_or(|size| size > self.memory_size) {
+            return Err(MikuError::InvalidConfig(format!(
+                "STACK ({}) AND DATA ({}) DON'T FIT INTO MEMORY ({})", stack_size, data_size, self.memory_size
+            )));
+        }
+
+        let layout = MemoryLayout {
+            stack: 0..stack_size,
+            data: stack_size..stack_size + data_size,
+            heap: stack_size + data_size..self.memory_size,
+        };
+
+        Ok(VmConfig { layout, byte_memory_size: self.byte_memory_size, gc_threshold: self.gc_threshold, gas_limit: self.gas_limit })
+    }
+}
+
+/// 30% of the memory size without overflowing for huge memories.
+fn default_segment_size(memory_size: usize) -> usize {
+    memory_size.checked_mul(3).map_or(memory_size / 10 * 3, |size| size / 10)
+}
+
+impl Default for VmConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
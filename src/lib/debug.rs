@@ -0,0 +1,189 @@
+//! Breakpoints and watchpoints.
+//!
+//! A [`crate::miku::MikuVM`] pauses before executing an instruction that has a breakpoint and
+//! after executing an instruction that accessed memory watched by a watchpoint. The pause is
+//! reported as [`crate::miku::Status::Paused`] by the stepping methods of the vm.
+//!
+//! ## Examples
+//! ``` rust
+//! use vm::{debug::{Pause, WatchKind}, inst::*, miku::{MikuVM, Status}, types::MikuType, STACK_START};
+//!
+//! let mut vm = MikuVM::from_program(vec![
+//!     Box::new(Push::new(MikuType::U8(1))),
+//!     Box::new(Push::new(MikuType::U8(2))),
+//! ]);
+//! vm.add_breakpoint(1);
+//! let watchpoint = vm.add_watchpoint(STACK_START..STACK_START + 1, WatchKind::Write);
+//!
+//! assert!(matches!(vm.run(), Status::Paused(Pause::Watchpoint { address: 0, .. })));
+//! assert!(matches!(vm.run(), Status::Paused(Pause::Breakpoint(1))));
+//! assert!(matches!(vm.run(), Status::Halted));
+//! ```
+
+use std::{collections::{BTreeMap, BTreeSet}, ops::Range};
+
+/// The kind of memory access a watchpoint fires on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl WatchKind {
+    /// Returns true if a watchpoint of this kind fires on the given access.
+    pub fn matches(self, access: WatchKind) -> bool {
+        self == WatchKind::ReadWrite || access == WatchKind::ReadWrite || self == access
+    }
+}
+
+/// A watched range of memory addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    range: Range<usize>,
+    kind: WatchKind,
+    /// Set if the range holds offsets into the byte memory instead of cell addresses.
+    bytes: bool,
+}
+
+impl Watchpoint {
+    pub fn new(range: Range<usize>, kind: WatchKind) -> Self {
+        Self { range, kind, bytes: false }
+    }
+
+    /// Creates a watchpoint on the given offsets of the byte memory.
+    pub fn bytes(range: Range<usize>, kind: WatchKind) -> Self {
+        Self { range, kind, bytes: true }
+    }
+
+    /// The watched addresses.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Returns true if the watched addresses are offsets into the byte memory.
+    pub fn is_bytes(&self) -> bool {
+        self.bytes
+    }
+
+    /// The accesses the watchpoint fires on.
+    pub fn kind(&self) -> WatchKind {
+        self.kind
+    }
+}
+
+/// Why the vm paused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pause {
+    /// The instruction at the program counter has a breakpoint. It hasn't been executed yet.
+    Breakpoint(usize),
+    /// The last executed instruction accessed a watched address.
+    Watchpoint {
+        /// The id returned when the watchpoint was added.
+        id: usize,
+        /// The accessed address, a byte offset for watchpoints on the byte memory.
+        address: usize,
+        /// [`WatchKind::Read`] or [`WatchKind::Write`].
+        access: WatchKind,
+    },
+}
+
+/// The breakpoints and watchpoints of a vm.
+#[derive(Debug, Clone, Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeMap<usize, Watchpoint>,
+    next_watchpoint_id: usize,
+    /// The first watchpoint that fired during the current instruction.
+    hit: Option<Pause>,
+    /// The breakpoint the vm last paused at. It is skipped once so execution can continue.
+    resume_from: Option<usize>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a breakpoint on the instruction at the given program counter.
+    /// # Returns
+    /// - `true` if there was no breakpoint there yet.
+    pub fn add_breakpoint(&mut self, pc: usize) -> bool {
+        self.breakpoints.insert(pc)
+    }
+
+    /// Removes the breakpoint at the given program counter.
+    /// # Returns
+    /// - `true` if there was a breakpoint there.
+    pub fn remove_breakpoint(&mut self, pc: usize) -> bool {
+        self.breakpoints.remove(&pc)
+    }
+
+    /// The program counters that have breakpoints.
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Adds a watchpoint.
+    /// # Returns
+    /// - The id of the watchpoint.
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> usize {
+        let id = self.next_watchpoint_id;
+        self.next_watchpoint_id += 1;
+        self.watchpoints.insert(id, watchpoint);
+        id
+    }
+
+    /// Removes the watchpoint with the given id.
+    /// # Returns
+    /// - `Some(Watchpoint)` the removed watchpoint.
+    /// - `None` if there is no watchpoint with the id.
+    pub fn remove_watchpoint(&mut self, id: usize) -> Option<Watchpoint> {
+        self.watchpoints.remove(&id)
+    }
+
+    /// The watchpoints with their ids.
+    pub fn watchpoints(&self) -> impl Iterator<Item = (usize, &Watchpoint)> + '_ {
+        self.watchpoints.iter().map(|(id, watchpoint)| (*id, watchpoint))
+    }
+
+    /// Checks whether the instruction at the given program counter should pause the vm.
+    /// Pausing at a breakpoint arms it to be skipped on the next check so execution can resume.
+    pub fn check_breakpoint(&mut self, pc: usize) -> Option<Pause> {
+        if self.resume_from.take() == Some(pc) || !self.breakpoints.contains(&pc) {
+            return None;
+        }
+
+        self.resume_from = Some(pc);
+        Some(Pause::Breakpoint(pc))
+    }
+
+    /// Records an access of the given addresses. The first watchpoint that fires is kept until
+    /// [`Debugger::take_hit`] is called.
+    pub fn on_access(&mut self, addresses: Range<usize>, access: WatchKind) {
+        self.record(addresses, access, false);
+    }
+
+    /// Records an access of the given byte memory offsets like [`Debugger::on_access`].
+    pub fn on_byte_access(&mut self, offsets: Range<usize>, access: WatchKind) {
+        self.record(offsets, access, true);
+    }
+
+    fn record(&mut self, addresses: Range<usize>, access: WatchKind, bytes: bool) {
+        if self.hit.is_some() {
+            return;
+        }
+
+        self.hit = self.watchpoints.iter()
+            .filter(|(_, watchpoint)| watchpoint.bytes == bytes && watchpoint.kind.matches(access))
+            .find_map(|(id, watchpoint)| {
+                let start = addresses.start.max(watchpoint.range.start);
+                (start < addresses.end.min(watchpoint.range.end)).then_some(Pause::Watchpoint { id: *id, address: start, access })
+            });
+    }
+
+    /// Returns and clears the watchpoint that fired during the current instruction.
+    pub fn take_hit(&mut self) -> Option<Pause> {
+        self.hit.take()
+    }
+}
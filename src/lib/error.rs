@@ -1,6 +1,6 @@
 use thiserror::Error;
 
-use crate::types::MikuType;
+use crate::{miku::StackFrame, types::{MikuType, Segment}};
 
 #[derive(Debug, Error)]
 pub enum MikuError {
@@ -9,12 +9,40 @@ pub enum MikuError {
     UnknownTypeError(u8),
     #[error("BYTE CONVERSION ERROR")]
     BytesConversionError,
+    #[error("INTEGER CONVERSION ERROR: {}", ._0)]
+    IntegerConversionError(String),
 
     /// Operation errors
     #[error("UNDEFINED OPERATION BETWEEN TYPES: {}", ._0)]
     UndefinedOperationBetweenTypesError(String),
     #[error("DIVISION BY ZERO")]
     DivisionByZeroError,
+    #[error("ARITHMETIC OVERFLOW: {}", ._0)]
+    ArithmeticOverflow(String),
+
+    /// Program errors
+    #[error("UNKNOWN OPCODE: {}", ._0)]
+    UnknownOpcode(u8),
+    #[error("UNKNOWN EXTENDED OPCODE: {}", ._0)]
+    UnknownExtendedOpcode(u16),
+    #[error("OPCODE ALREADY REGISTERED: {}", ._0)]
+    OpcodeAlreadyRegistered(u16),
+
+    /// Execution errors
+    #[error("OUT OF GAS: {} NEEDED", ._0)]
+    OutOfGas(u64),
+    #[error("INTERRUPTED")]
+    Interrupted,
+
+    /// Snapshot errors
+    #[error("INVALID SNAPSHOT: {}", ._0)]
+    InvalidSnapshot(String),
+    #[error("UNSUPPORTED SNAPSHOT VERSION: {}", ._0)]
+    UnsupportedSnapshotVersion(u16),
+
+    /// Configuration errors
+    #[error("INVALID CONFIG: {}", ._0)]
+    InvalidConfig(String),
 
     /// Stack errors
     #[error("STACK OVERFLOW")]
@@ -29,4 +57,93 @@ pub enum MikuError {
     SegmentationFault,
     #[error("INVALID POINTER TYPE: {:?}", ._0)]
     InvalidPointerType(MikuType),
+    #[error("PROTECTION FAULT: {:?} SEGMENT IS READ-ONLY", ._0)]
+    ProtectionFault(Segment),
+    #[error("INVALID MAPPING: {}..{}", ._0, ._1)]
+    InvalidMapping(usize, usize),
+    #[error("OUT OF MEMORY: CAN'T ALLOCATE {} CELLS", ._0)]
+    OutOfMemory(usize),
+    #[error("INVALID FREE: {}", ._0)]
+    InvalidFree(usize),
+    #[error("MISALIGNED ACCESS: {} BYTES AT OFFSET {}", ._0, ._1)]
+    MisalignedAccess(usize, usize),
+    #[error("UNSUPPORTED TYPE: {}", ._0)]
+    UnsupportedTypeError(u8),
+    #[error("NOT A BIG INTEGER: {:?}", ._0)]
+    InvalidBigInt(MikuType),
+    #[error("DEVICE TYPE MISMATCH: EXPECTED TYPE {}, FOUND {:?}", ._0, ._1)]
+    DeviceTypeMismatch(u8, MikuType),
+
+    /// Struct errors
+    #[error("INVALID STRUCT LAYOUT: {}", ._0)]
+    InvalidStructLayout(String),
+    #[error("UNDEFINED STRUCT: {}", ._0)]
+    UndefinedStruct(usize),
+    #[error("STRUCT REDEFINITION: {}", ._0)]
+    StructRedefinition(usize),
+    #[error("UNDEFINED FIELD: {} OF STRUCT {}", ._1, ._0)]
+    UndefinedField(usize, usize),
+    #[error("FIELD TYPE MISMATCH: EXPECTED TYPE {}, FOUND {:?}", ._0, ._1)]
+    FieldTypeMismatch(u8, MikuType),
+}
+
+/// A [`MikuError`] that stopped the execution of a program together with where it happened.
+#[derive(Debug, Error)]
+#[error("{} AT PC {} ({}){}", .error, .pc, .inst, .address.map(|address| format!(" ADDRESS {}", address)).unwrap_or_default())]
+pub struct RuntimeError {
+    #[source]
+    error: Box<MikuError>,
+    pc: usize,
+    inst: String,
+    address: Option<usize>,
+    backtrace: Vec<StackFrame>,
+}
+
+impl RuntimeError {
+    pub fn new(error: MikuError, pc: usize, inst: String, address: Option<usize>, backtrace: Vec<StackFrame>) -> Self {
+        Self { error: Box::new(error), pc, inst, address, backtrace }
+    }
+
+    /// The error the instruction failed with.
+    pub fn error(&self) -> &MikuError {
+        &self.error
+    }
+
+    /// Consumes the runtime error and returns the error the instruction failed with.
+    pub fn into_error(self) -> MikuError {
+        *self.error
+    }
+
+    /// The program counter of the failed instruction.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// The disassembly of the failed instruction. See [`crate::inst::Inst::disassemble`].
+    pub fn inst(&self) -> &str {
+        &self.inst
+    }
+
+    /// The memory address the instruction faulted at if the error was caused by a memory access.
+    pub fn address(&self) -> Option<usize> {
+        self.address
+    }
+
+    /// The stack frames at the time of the failure, innermost first.
+    pub fn backtrace(&self) -> &[StackFrame] {
+        &self.backtrace
+    }
+}
+
+/// Errors of parsing a [`MikuType`] from text.
+#[derive(Debug, Error, PartialEq)]
+pub enum ParseTypeError {
+    #[error("EXPECTED: <type> <value>, RECEIVED: {:?}", ._0)]
+    InvalidFormat(String),
+    #[error("UNKNOWN TYPE: {}", ._0)]
+    UnknownType(String),
+    #[error("INVALID {} LITERAL: {}", ._0, ._1)]
+    InvalidLiteral(String, String),
+    #[error("UNKNOWN SEGMENT: {}", ._0)]
+    UnknownSegment(String),
 }
@@ -0,0 +1,72 @@
+//! Gas metering.
+//!
+//! When a [`crate::miku::MikuVM`] has a gas budget every executed instruction is charged the
+//! cost its opcode has in the [`GasTable`]. Allocations are additionally charged for every
+//! allocated cell. Execution stops with [`crate::error::MikuError::OutOfGas`] once the budget
+//! can't pay for the next instruction.
+//!
+//! ## Examples
+//! ``` rust
+//! use vm::{gas::GasTable, inst::*, miku::{MikuVM, Status}, types::MikuType};
+//!
+//! let mut vm = MikuVM::from_program(vec![
+//!     Box::new(Push::new(MikuType::U8(1))),
+//!     Box::new(Push::new(MikuType::U8(2))),
+//! ]);
+//! vm.set_gas(Some(1));
+//! assert!(matches!(vm.run_for(2), Status::Trapped(_)));
+//! vm.add_gas(1);
+//! assert!(matches!(vm.run_for(2), Status::Halted));
+//! assert_eq!(Some(0), vm.gas());
+//! ```
+
+/// The gas costs of the instructions indexed by opcode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasTable {
+    costs: [u64; 256],
+    alloc_cell_cost: u64,
+}
+
+impl GasTable {
+    /// Creates a table where every instruction costs 1 gas and allocated cells are free.
+    pub fn flat() -> Self {
+        Self { costs: [1; 256], alloc_cell_cost: 0 }
+    }
+
+    /// The cost of the instruction with the given opcode.
+    pub fn cost(&self, opcode: u8) -> u64 {
+        self.costs[opcode as usize]
+    }
+
+    /// Sets the cost of the instruction with the given opcode.
+    pub fn set_cost(&mut self, opcode: u8, cost: u64) {
+        self.costs[opcode as usize] = cost;
+    }
+
+    /// The extra cost of every cell allocated on the heap.
+    pub fn alloc_cell_cost(&self) -> u64 {
+        self.alloc_cell_cost
+    }
+
+    /// Sets the extra cost of every cell allocated on the heap.
+    pub fn set_alloc_cell_cost(&mut self, alloc_cell_cost: u64) {
+        self.alloc_cell_cost = alloc_cell_cost;
+    }
+}
+
+/// Stack and arithmetic instructions cost 1 gas, instructions that access memory cost 3 and
+/// allocations, including the big integer instructions that allocate their result, cost 5 plus
+/// 1 for every allocated cell.
+impl Default for GasTable {
+    fn default() -> Self {
+        let mut table = Self::flat();
+        for opcode in [0x02, 0x03, 0x04, 0x05, 0x0A, 0x0B, 0x0D, 0x14, 0x15] {
+            table.set_cost(opcode, 3);
+        }
+        for opcode in [0x0C, 0x0E, 0x0F, 0x10, 0x11, 0x12, 0x13] {
+            table.set_cost(opcode, 5);
+        }
+        table.set_alloc_cell_cost(1);
+        table
+    }
+}
@@ -0,0 +1,207 @@
+//! Heap allocation and garbage collection.
+//!
+//! [`Heap`] keeps track of the blocks allocated in the heap segment of the memory. Blocks can be
+//! freed manually or reclaimed by a mark-and-sweep collector: every block that can't be reached
+//! by following [`MikuType::Ptr`] values from the roots (the stack and the .data segment) is
+//! freed. Pointers into the middle of a block keep the whole block alive.
+
+use std::{collections::BTreeMap, ops::Range};
+
+use crate::{error::MikuError, types::{MikuType, Segment}};
+
+/// Statistics of the garbage collector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GcStats {
+    /// The number of collections that ran.
+    pub collections: usize,
+    /// The number of blocks freed by all collections.
+    pub freed_blocks: usize,
+    /// The number of cells freed by all collections.
+    pub freed_cells: usize,
+    /// The number of blocks currently allocated.
+    pub live_blocks: usize,
+    /// The number of cells currently allocated.
+    pub live_cells: usize,
+}
+
+/// The allocator of the heap segment.
+#[derive(Debug, Clone)]
+pub struct Heap {
+    /// The addresses of the heap segment.
+    range: Range<usize>,
+    /// The allocated blocks, start address mapped to size in cells.
+    blocks: BTreeMap<usize, usize>,
+    /// Ranges that are never allocated (e.g. mapped devices), start address mapped to size.
+    reserved: BTreeMap<usize, usize>,
+    /// Collect automatically once allocating would make more cells live than this.
+    /// `None` disables automatic collection.
+    gc_threshold: Option<usize>,
+    stats: GcStats,
+}
+
+impl Heap {
+    pub fn new(range: Range<usize>, gc_threshold: Option<usize>) -> Self {
+        Self { range, blocks: BTreeMap::new(), reserved: BTreeMap::new(), gc_threshold, stats: GcStats::default() }
+    }
+
+    /// The allocated blocks as start address and size in cells.
+    pub fn blocks(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.blocks.iter().map(|(start, size)| (*start, *size))
+    }
+
+    /// The statistics of the collector.
+    pub fn stats(&self) -> GcStats {
+        self.stats
+    }
+
+    /// The automatic collection threshold.
+    pub fn gc_threshold(&self) -> Option<usize> {
+        self.gc_threshold
+    }
+
+    /// Sets the automatic collection threshold. `None` disables automatic collection.
+    pub fn set_gc_threshold(&mut self, gc_threshold: Option<usize>) {
+        self.gc_threshold = gc_threshold;
+    }
+
+    /// Returns true if allocating the given number of cells should trigger a collection first.
+    pub fn needs_collection(&self, size: usize) -> bool {
+        match self.gc_threshold {
+            Some(threshold) => self.stats.live_cells.saturating_add(size) > threshold || self.find_free(size).is_none(),
+            None => false,
+        }
+    }
+
+    /// Allocates a block of the given number of cells (at least one) with a first fit strategy.
+    ///
+    /// # Returns
+    /// - `Ok(address)` the start address of the block.
+    /// - [`MikuError::OutOfMemory`] if there is no free space large enough.
+    pub fn alloc(&mut self, size: usize) -> Result<usize, MikuError> {
+        let size = size.max(1);
+        let start = self.find_free(size).ok_or(MikuError::OutOfMemory(size))?;
+
+        self.blocks.insert(start, size);
+        self.stats.live_blocks += 1;
+        self.stats.live_cells += size;
+        Ok(start)
+    }
+
+    /// Frees the block starting at the given address.
+    ///
+    /// # Returns
+    /// - `Ok(block)` the addresses of the freed block.
+    /// - [`MikuError::InvalidFree`] if no block starts at the address.
+    pub fn free(&mut self, address: usize) -> Result<Range<usize>, MikuError> {
+        let size = self.blocks.remove(&address).ok_or(MikuError::InvalidFree(address))?;
+
+        self.stats.live_blocks -= 1;
+        self.stats.live_cells -= size;
+        Ok(address..address + size)
+    }
+
+    /// Excludes the given range from allocation.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the range was reserved.
+    /// - [`MikuError::InvalidMapping`] if the range is empty, outside of the heap or overlaps an
+    ///   allocated block or another reserved range.
+    pub fn reserve(&mut self, range: Range<usize>) -> Result<(), MikuError> {
+        let overlaps = self.blocks.iter()
+            .chain(&self.reserved)
+            .any(|(start, size)| *start < range.end && range.start < start + size);
+
+        if range.is_empty() || range.start < self.range.start || range.end > self.range.end || overlaps {
+            return Err(MikuError::InvalidMapping(range.start, range.end));
+        }
+
+        self.reserved.insert(range.start, range.len());
+        Ok(())
+    }
+
+    /// Makes the reserved range starting at the given address allocatable again.
+    pub fn release(&mut self, start: usize) {
+        self.reserved.remove(&start);
+    }
+
+    /// Replaces the allocated blocks and the statistics, e.g. when restoring a snapshot.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the blocks were restored.
+    /// - [`MikuError::InvalidSnapshot`] if a block is empty, outside of the heap or overlaps
+    ///   another block, or the live blocks and cells of the statistics don't match the blocks.
+    pub fn restore_blocks(&mut self, blocks: Vec<(usize, usize)>, stats: GcStats) -> Result<(), MikuError> {
+        self.blocks.clear();
+        let mut end = self.range.start;
+
+        for (start, size) in blocks {
+            if size == 0 || start < end || start.checked_add(size).is_none_or(|block_end| block_end > self.range.end) {
+                return Err(MikuError::InvalidSnapshot(format!("INVALID HEAP BLOCK {} ({})", start, size)));
+            }
+            self.blocks.insert(start, size);
+            end = start + size;
+        }
+
+        let live_cells: usize = self.blocks.values().sum();
+        if stats.live_blocks != self.blocks.len() || stats.live_cells != live_cells {
+            self.blocks.clear();
+            return Err(MikuError::InvalidSnapshot("HEAP STATISTICS DON'T MATCH THE BLOCKS".to_string()));
+        }
+
+        self.stats = stats;
+        Ok(())
+    }
+
+    /// Returns the block that contains the given address.
+    pub fn block_of(&self, address: usize) -> Option<Range<usize>> {
+        let (start, size) = self.blocks.range(..=address).next_back()?;
+        (address < start + size).then(|| *start..start + size)
+    }
+
+    /// Runs a mark-and-sweep collection.
+    /// The blocks reachable from the roots are kept, every other block is freed. The cells of
+    /// the freed blocks are left to the caller, which resets them to [`MikuType::NULL`].
+    ///
+    /// # Returns
+    /// - The address ranges of the freed blocks.
+    pub fn collect(&mut self, memory: &[MikuType], roots: impl Iterator<Item = MikuType>) -> Vec<Range<usize>> {
+        let mut marked: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut pending: Vec<MikuType> = roots.collect();
+
+        while let Some(value) = pending.pop() {
+            let MikuType::Ptr(pointer) = value else { continue };
+            if pointer.segment() != Segment::Heap {
+                continue;
+            }
+
+            let Some(block) = usize::try_from(pointer.address()).ok().and_then(|address| self.block_of(address)) else { continue };
+            if marked.insert(block.start, block.len()).is_none() {
+                pending.extend(&memory[block]);
+            }
+        }
+
+        let garbage: Vec<usize> = self.blocks.keys().filter(|start| !marked.contains_key(start)).copied().collect();
+        let freed: Vec<Range<usize>> = garbage.into_iter().filter_map(|start| self.free(start).ok()).collect();
+
+        self.stats.collections += 1;
+        self.stats.freed_blocks += freed.len();
+        self.stats.freed_cells += freed.iter().map(|block| block.len()).sum::<usize>();
+        freed
+    }
+
+    /// Finds the first gap between blocks that fits the given number of cells.
+    fn find_free(&self, size: usize) -> Option<usize> {
+        let mut taken: Vec<(&usize, &usize)> = self.blocks.iter().chain(&self.reserved).collect();
+        taken.sort_unstable();
+        let mut candidate = self.range.start;
+
+        for (start, block_size) in taken {
+            if start - candidate >= size {
+                return Some(candidate);
+            }
+            candidate = start + block_size;
+        }
+
+        (self.range.end.saturating_sub(candidate) >= size).then_some(candidate)
+    }
+}
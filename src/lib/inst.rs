@@ -5,16 +5,19 @@
 //! holds a vector of elements that implement this trait. This is achieved with
 //! dynamic dispatching.
 
-use std::{fmt::Debug, usize};
+use std::fmt::Debug;
 
 use crate::{
-    error::MikuError, miku::MikuVM, tools, types::MikuType
+    bigint::BigInt, error::MikuError, layout::StructLayout, miku::MikuVM, tools, types::MikuType
 };
 
 /// # The instruction trait.
 ///
 /// This trait needs to be implemented by anything that wants to be executed by [`MikuVM`].
-pub trait Inst: Debug {
+/// Custom instructions are encoded with [`crate::program::encode_extended`] and registered in a
+/// [`crate::program::InstRegistry`] to be decodable from bytecode. Instructions are shared
+/// between threads by [`crate::program::Program`] so they have to be [`Send`] and [`Sync`].
+pub trait Inst: Debug + Send + Sync {
     /// This method gets called by [`MikuVM::run_program()`] when the instruction needs to be
     /// executed.
     /// # Returns
@@ -40,6 +43,32 @@ pub trait Inst: Debug {
     /// - `Ok(Self)` ([`Inst`]) if the decoding was successful.
     /// - [`MikuError`] if something goes wrong during decoding.
     fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized;
+
+    /// Returns the instruction in a human readable form, e.g. `push u8 69`.
+    /// The mnemonics are listed in the instruction table of the crate.
+    fn disassemble(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    /// The opcode of the instruction. This is the first byte of its encoding.
+    /// The default encodes the instruction, so instructions should return their opcode directly.
+    fn opcode(&self) -> u8 {
+        self.encode()[0]
+    }
+
+    /// The gas the instruction costs in the current state of the vm.
+    /// [`MikuVM::step`] charges it before the instruction is executed. Defaults to the cost of
+    /// the opcode in the vm's [`crate::gas::GasTable`].
+    fn gas_cost(&self, vm: &MikuVM) -> u64 {
+        vm.gas_table().cost(self.opcode())
+    }
+
+    /// Returns true if the instruction belongs to the initialisation phase of a program, where
+    /// the .data segment is filled. [`MikuVM::run_program()`] seals the .data segment once the
+    /// first instruction that doesn't is executed.
+    fn initialises_data(&self) -> bool {
+        false
+    }
 }
 
 /// # Push instruction.
@@ -69,6 +98,7 @@ impl Inst for Push {
     
     /// # Example
     /// ``` rust
+    /// # use vm::{inst::*, types::MikuType};
     /// let push = Push::new(MikuType::U8(69)); 
     /// let encoded_push = push.encode();
     /// assert_eq!(vec![0x00, 0x00, 0x45], encoded_push);
@@ -83,6 +113,7 @@ impl Inst for Push {
     
     /// # Example
     /// ``` rust
+    /// # use vm::{inst::*, types::MikuType};
     /// let encoded_push = vec![0x00, 0x00, 0x45];
     /// let decoded_push = Push::decode(&encoded_push).unwrap(); 
     /// assert_eq!(Push::new(MikuType::U8(69)), decoded_push);
@@ -91,6 +122,14 @@ impl Inst for Push {
         let operand = MikuType::try_from(&bytes[1..bytes.len()])?;
         Ok(Push::new(operand))
     }
+
+    fn opcode(&self) -> u8 {
+        0x00
+    }
+
+    fn disassemble(&self) -> String {
+        format!("push {}", self.operand)
+    }
 }
 
  
@@ -111,15 +150,23 @@ impl Pop {
     }
 }
 
+impl Default for Pop {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Inst for Pop {
     fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
         vm.inc_pc();
-        vm.stack_pop()
+        vm.stack_pop()?;
+        Ok(())
     }
     
     /// # Example
     ///
     /// ``` rust
+    /// # use vm::inst::*;
     /// let pop = Pop::new();
     /// let encoded_pop = pop.encode();
     /// assert_eq!(vec![0x01], encoded_pop);
@@ -132,6 +179,7 @@ impl Inst for Pop {
     /// # Example
     ///
     /// ``` rust
+    /// # use vm::inst::*;
     /// assert_eq!(Pop::new(), Pop::decode(&vec![0x01]).unwrap());
     /// ```
     fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
@@ -140,6 +188,14 @@ impl Inst for Pop {
         }
         Ok(Pop::new())
     }
+
+    fn opcode(&self) -> u8 {
+        0x01
+    }
+
+    fn disassemble(&self) -> String {
+        "pop".to_string()
+    }
 }
 
 /// # Def instruction.
@@ -168,10 +224,15 @@ impl Inst for Def {
         vm.inc_pc();
         vm.define_data(self.operand_1, self.opreand_2)
     }
+
+    fn initialises_data(&self) -> bool {
+        true
+    }
     
     /// # Example
     ///
     /// ``` rust
+    /// # use vm::{inst::*, types::MikuType};
     /// let def = Def::new(MikuType::U8(69), 1);
     /// let encoded_def = def.encode();
     /// assert_eq!(
@@ -192,7 +253,11 @@ impl Inst for Def {
     /// # Example
     ///
     /// ``` rust
-    /// assert_eq!(Def::new(MikuType::U8(69)), Def::decode(&vec![0x02, 0x00, 0x45, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]));
+    /// # use vm::{inst::*, types::MikuType};
+    /// assert_eq!(
+    ///    Def::new(MikuType::U8(69), 1),
+    ///    Def::decode(&vec![0x02, 0x00, 0x45, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]).unwrap()
+    /// );
     /// ```
     fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
         let operand_1_length: usize = MikuType::get_bytes_length(bytes[1])?;
@@ -200,4 +265,729 @@ impl Inst for Def {
         let opreand_2 = usize::from_le_bytes(tools::convert_bytes(&bytes[operand_1_length + 1..bytes.len()])?);
         Ok(Def::new(operand_1, opreand_2))
     }
+
+    fn opcode(&self) -> u8 {
+        0x02
+    }
+
+    fn disassemble(&self) -> String {
+        format!("def {} {}", self.operand_1, self.opreand_2)
+    }
+}
+
+/// # DefStruct instruction.
+///
+/// Declares a [`StructLayout`] under the given id so [`FieldGet`] and [`FieldSet`] can use it.
+///
+/// ## Information
+/// - Opcode: 3
+/// - Operands:
+///   - id ([`prim@usize`])
+///   - [`StructLayout`]
+#[derive(Debug, PartialEq)]
+pub struct DefStruct {
+    operand_1: usize,
+    operand_2: StructLayout,
+}
+
+impl DefStruct {
+    pub fn new(operand_1: usize, operand_2: StructLayout) -> Self {
+        Self { operand_1, operand_2 }
+    }
+}
+
+impl Inst for DefStruct {
+    fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+        vm.inc_pc();
+        vm.define_struct(self.operand_1, self.operand_2.clone())
+    }
+
+    /// # Example
+    ///
+    /// ``` rust
+    /// # use vm::{inst::*, layout::*};
+    /// let layout = StructLayout::new("p", vec![Field::new("x", 0x00, 0)]).unwrap();
+    /// assert_eq!(
+    ///    vec![0x03, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ///         0x01, 0x70, 0x01, 0x01, 0x78, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+    ///    DefStruct::new(1, layout).encode()
+    /// );
+    /// ```
+    fn encode(&self) -> Vec<u8> {
+        let opcode: u8 = 0x03;
+        let mut encoded_def_struct = vec![opcode];
+        encoded_def_struct.extend(self.operand_1.to_le_bytes());
+        encoded_def_struct.extend(Vec::from(&self.operand_2));
+        encoded_def_struct
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+        let layout_start = 1 + size_of::<usize>();
+        if bytes.len() < layout_start {
+            return Err(MikuError::BytesConversionError);
+        }
+
+        let operand_1 = usize::from_le_bytes(tools::convert_bytes(&bytes[1..layout_start])?);
+        let operand_2 = StructLayout::try_from(&bytes[layout_start..])?;
+        Ok(DefStruct::new(operand_1, operand_2))
+    }
+
+    fn opcode(&self) -> u8 {
+        0x03
+    }
+
+    fn disassemble(&self) -> String {
+        format!("defstruct {} {}", self.operand_1, self.operand_2)
+    }
+}
+
+/// Encodes an instruction that has an opcode and two [`prim@usize`] operands.
+fn encode_usize_pair(opcode: u8, operand_1: usize, operand_2: usize) -> Vec<u8> {
+    let mut encoded_instruction = vec![opcode];
+    encoded_instruction.extend(operand_1.to_le_bytes());
+    encoded_instruction.extend(operand_2.to_le_bytes());
+    encoded_instruction
+}
+
+/// Decodes the two [`prim@usize`] operands of an instruction encoded by [`encode_usize_pair`].
+fn decode_usize_pair(bytes: &[u8]) -> Result<(usize, usize), MikuError> {
+    if bytes.len() != 1 + 2 * size_of::<usize>() {
+        return Err(MikuError::BytesConversionError);
+    }
+
+    let operand_1 = usize::from_le_bytes(tools::convert_bytes(&bytes[1..1 + size_of::<usize>()])?);
+    let operand_2 = usize::from_le_bytes(tools::convert_bytes(&bytes[1 + size_of::<usize>()..])?);
+    Ok((operand_1, operand_2))
+}
+
+/// # FieldGet instruction.
+///
+/// Pops a pointer to a record off the stack and pushes the value of one of its fields.
+/// The value is checked against the type declared in the record's [`StructLayout`].
+///
+/// ## Information
+/// - Opcode: 4
+/// - Operands:
+///   - struct id ([`prim@usize`])
+///   - field index ([`prim@usize`])
+#[derive(Debug, PartialEq)]
+pub struct FieldGet {
+    operand_1: usize,
+    operand_2: usize,
+}
+
+impl FieldGet {
+    pub fn new(operand_1: usize, operand_2: usize) -> Self {
+        Self { operand_1, operand_2 }
+    }
+}
+
+impl Inst for FieldGet {
+    fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+        vm.inc_pc();
+        let ptr = vm.stack_pop()?;
+        let value = vm.get_field(ptr, self.operand_1, self.operand_2)?;
+        vm.stack_push(value)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        encode_usize_pair(0x04, self.operand_1, self.operand_2)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+        let (operand_1, operand_2) = decode_usize_pair(bytes)?;
+        Ok(FieldGet::new(operand_1, operand_2))
+    }
+
+    fn opcode(&self) -> u8 {
+        0x04
+    }
+
+    fn disassemble(&self) -> String {
+        format!("fget {} {}", self.operand_1, self.operand_2)
+    }
+}
+
+/// # FieldSet instruction.
+///
+/// Pops a value and then a pointer to a record off the stack and writes the value into one of
+/// the record's fields. The value is checked against the type declared in the record's [`StructLayout`].
+///
+/// ## Information
+/// - Opcode: 5
+/// - Operands:
+///   - struct id ([`prim@usize`])
+///   - field index ([`prim@usize`])
+#[derive(Debug, PartialEq)]
+pub struct FieldSet {
+    operand_1: usize,
+    operand_2: usize,
+}
+
+impl FieldSet {
+    pub fn new(operand_1: usize, operand_2: usize) -> Self {
+        Self { operand_1, operand_2 }
+    }
+}
+
+impl Inst for FieldSet {
+    fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+        vm.inc_pc();
+        let value = vm.stack_pop()?;
+        let ptr = vm.stack_pop()?;
+        vm.set_field(ptr, self.operand_1, self.operand_2, value)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        encode_usize_pair(0x05, self.operand_1, self.operand_2)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+        let (operand_1, operand_2) = decode_usize_pair(bytes)?;
+        Ok(FieldSet::new(operand_1, operand_2))
+    }
+
+    fn opcode(&self) -> u8 {
+        0x05
+    }
+
+    fn disassemble(&self) -> String {
+        format!("fset {} {}", self.operand_1, self.operand_2)
+    }
+}
+
+/// Used to implement the arithmetic instructions.
+/// Each of them pops the right hand side and then the left hand side operand off the stack,
+/// promotes them according to the vm's [`crate::types::Promotion`] policy and pushes the result.
+macro_rules! impl_arith_inst {
+    ($name: ident, $opcode: expr, $operator: tt, $doc: expr) => {
+        #[doc = concat!("# ", stringify!($name), " instruction.")]
+        ///
+        #[doc = $doc]
+        ///
+        /// ## Information
+        #[doc = concat!("- Opcode: ", $opcode)]
+        /// - Operands:
+        ///   - None
+        #[derive(Debug, PartialEq)]
+        pub struct $name { }
+
+        impl $name {
+            pub fn new() -> Self {
+                Self { }
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl Inst for $name {
+            fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+                vm.inc_pc();
+                let rhs = vm.stack_pop()?;
+                let lhs = vm.stack_pop()?;
+                let (lhs, rhs) = vm.promote(lhs, rhs)?;
+                vm.stack_push((lhs $operator rhs)?)
+            }
+
+            fn encode(&self) -> Vec<u8> {
+                vec![$opcode]
+            }
+
+            fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+                if bytes.len() != 1 {
+                    return Err(MikuError::BytesConversionError);
+                }
+                Ok($name::new())
+            }
+
+            fn opcode(&self) -> u8 {
+                $opcode
+            }
+
+            fn disassemble(&self) -> String {
+                stringify!($name).to_lowercase()
+            }
+        }
+    };
+}
+
+impl_arith_inst!(Plus, 6, +, "Pops two entries off the stack and pushes their sum.");
+impl_arith_inst!(Minus, 7, -, "Pops two entries off the stack and pushes their difference.");
+impl_arith_inst!(Mult, 8, *, "Pops two entries off the stack and pushes their product.");
+impl_arith_inst!(Div, 9, /, "Pops two entries off the stack and pushes their quotient.");
+
+/// # Load instruction.
+///
+/// Pops a pointer into the byte-addressed memory ([`crate::types::Segment::Bytes`]) off the
+/// stack, loads a value of the given type from there and pushes it onto the stack.
+///
+/// ## Information
+/// - Opcode: 10
+/// - Operands:
+///   - type identifier ([`prim@u8`])
+#[derive(Debug, PartialEq)]
+pub struct Load {
+    operand: u8,
+}
+
+impl Load {
+    pub fn new(operand: u8) -> Self {
+        Self { operand }
+    }
+}
+
+impl Inst for Load {
+    fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+        vm.inc_pc();
+        let ptr = vm.stack_pop()?;
+        let value = vm.load_bytes(ptr, self.operand)?;
+        vm.stack_push(value)
+    }
+
+    /// # Example
+    ///
+    /// ``` rust
+    /// # use vm::inst::*;
+    /// assert_eq!(vec![0x0A, 0x02], Load::new(0x02).encode());
+    /// ```
+    fn encode(&self) -> Vec<u8> {
+        vec![0x0A, self.operand]
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+        if bytes.len() != 2 {
+            return Err(MikuError::BytesConversionError);
+        }
+        Ok(Load::new(bytes[1]))
+    }
+
+    fn opcode(&self) -> u8 {
+        0x0A
+    }
+
+    fn disassemble(&self) -> String {
+        format!("load {}", MikuType::type_name(self.operand).unwrap_or("?"))
+    }
+}
+
+/// # Store instruction.
+///
+/// Pops a value and then a pointer into the byte-addressed memory
+/// ([`crate::types::Segment::Bytes`]) off the stack and stores the value there.
+///
+/// ## Information
+/// - Opcode: 11
+/// - Operands:
+///   - None
+#[derive(Debug, PartialEq)]
+pub struct Store { }
+
+impl Store {
+    pub fn new() -> Self {
+        Self { }
+    }
+}
+
+impl Default for Store {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inst for Store {
+    fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+        vm.inc_pc();
+        let value = vm.stack_pop()?;
+        let ptr = vm.stack_pop()?;
+        vm.store_bytes(ptr, value)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        vec![0x0B]
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+        if bytes.len() != 1 {
+            return Err(MikuError::BytesConversionError);
+        }
+        Ok(Store::new())
+    }
+
+    fn opcode(&self) -> u8 {
+        0x0B
+    }
+
+    fn disassemble(&self) -> String {
+        "store".to_string()
+    }
+}
+
+/// # Alloc instruction.
+///
+/// Pops a size ([`MikuType::U64`]) off the stack, allocates that many cells on the heap and
+/// pushes a pointer to the block.
+///
+/// ## Information
+/// - Opcode: 12
+/// - Operands:
+///   - None
+#[derive(Debug, PartialEq)]
+pub struct Alloc { }
+
+impl Alloc {
+    pub fn new() -> Self {
+        Self { }
+    }
+}
+
+impl Default for Alloc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inst for Alloc {
+    fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+        vm.inc_pc();
+        let size = match vm.stack_pop()? {
+            MikuType::U64(size) => usize::try_from(size).map_err(|_| MikuError::OutOfMemory(usize::MAX))?,
+            size => return Err(MikuError::UndefinedOperationBetweenTypesError(format!("alloc({:?})", size))),
+        };
+        let ptr = vm.alloc(size)?;
+        vm.stack_push(ptr)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        vec![0x0C]
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+        if bytes.len() != 1 {
+            return Err(MikuError::BytesConversionError);
+        }
+        Ok(Alloc::new())
+    }
+
+    fn opcode(&self) -> u8 {
+        0x0C
+    }
+
+    /// The cost of the opcode plus the cost of every cell of the size on top of the stack.
+    fn gas_cost(&self, vm: &MikuVM) -> u64 {
+        let cells = match vm.stack_peek() {
+            Some(MikuType::U64(size)) => size,
+            _ => 0,
+        };
+        vm.gas_table().cost(self.opcode()).saturating_add(vm.gas_table().alloc_cell_cost().saturating_mul(cells))
+    }
+
+    fn disassemble(&self) -> String {
+        "alloc".to_string()
+    }
+}
+
+/// # Free instruction.
+///
+/// Pops a pointer off the stack and frees the heap block it points to.
+///
+/// ## Information
+/// - Opcode: 13
+/// - Operands:
+///   - None
+#[derive(Debug, PartialEq)]
+pub struct Free { }
+
+impl Free {
+    pub fn new() -> Self {
+        Self { }
+    }
+}
+
+impl Default for Free {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inst for Free {
+    fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+        vm.inc_pc();
+        let ptr = vm.stack_pop()?;
+        vm.free(ptr)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        vec![0x0D]
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+        if bytes.len() != 1 {
+            return Err(MikuError::BytesConversionError);
+        }
+        Ok(Free::new())
+    }
+
+    fn opcode(&self) -> u8 {
+        0x0D
+    }
+
+    fn disassemble(&self) -> String {
+        "free".to_string()
+    }
+}
+
+/// # ToBig instruction.
+///
+/// Pops an integer off the stack, allocates it as a big integer on the heap and pushes a
+/// pointer to it. See [`MikuVM::alloc_bigint`] for the layout of the block.
+///
+/// ## Information
+/// - Opcode: 14
+/// - Operands:
+///   - None
+#[derive(Debug, PartialEq)]
+pub struct ToBig { }
+
+impl ToBig {
+    pub fn new() -> Self {
+        Self { }
+    }
+}
+
+impl Default for ToBig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inst for ToBig {
+    fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+        vm.inc_pc();
+        let value = BigInt::try_from(vm.stack_pop()?)?;
+        let ptr = vm.alloc_bigint(&value)?;
+        vm.stack_push(ptr)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        vec![0x0E]
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+        if bytes.len() != 1 {
+            return Err(MikuError::BytesConversionError);
+        }
+        Ok(ToBig::new())
+    }
+
+    fn opcode(&self) -> u8 {
+        0x0E
+    }
+
+    /// The cost of the opcode plus the cost of every cell of the allocated block.
+    fn gas_cost(&self, vm: &MikuVM) -> u64 {
+        let cells = vm.stack_peek()
+            .and_then(|value| BigInt::try_from(value).ok())
+            .map_or(0, |value| value.limbs().len() as u64 + 1);
+        vm.gas_table().cost(self.opcode()).saturating_add(vm.gas_table().alloc_cell_cost().saturating_mul(cells))
+    }
+
+    fn disassemble(&self) -> String {
+        "tobig".to_string()
+    }
+}
+
+/// Used to implement the big integer arithmetic instructions.
+/// `$apply` computes the result and `$limbs` the most limbs the result can have given the
+/// limbs of the operands, which is charged like an allocation of that size.
+macro_rules! impl_big_arith_inst {
+    ($name: ident, $opcode: expr, $apply: expr, $limbs: expr, $doc: expr) => {
+        #[doc = concat!("# ", stringify!($name), " instruction.")]
+        ///
+        #[doc = $doc]
+        /// The operands are pointers to big integers and the result is allocated on the heap.
+        ///
+        /// ## Information
+        #[doc = concat!("- Opcode: ", $opcode)]
+        /// - Operands:
+        ///   - None
+        #[derive(Debug, PartialEq)]
+        pub struct $name { }
+
+        impl $name {
+            pub fn new() -> Self {
+                Self { }
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl Inst for $name {
+            fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+                let apply: fn(&BigInt, &BigInt) -> Result<BigInt, MikuError> = $apply;
+
+                vm.inc_pc();
+                let rhs = vm.stack_pop()?;
+                let lhs = vm.stack_pop()?;
+                let result = apply(&vm.read_bigint(lhs)?, &vm.read_bigint(rhs)?)?;
+                let ptr = vm.alloc_bigint(&result)?;
+                vm.stack_push(ptr)
+            }
+
+            fn encode(&self) -> Vec<u8> {
+                vec![$opcode]
+            }
+
+            fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+                if bytes.len() != 1 {
+                    return Err(MikuError::BytesConversionError);
+                }
+                Ok($name::new())
+            }
+
+            fn opcode(&self) -> u8 {
+                $opcode
+            }
+
+            /// The cost of the opcode plus the cost of every cell the largest possible result
+            /// takes.
+            fn gas_cost(&self, vm: &MikuVM) -> u64 {
+                let limbs: fn(u64, u64) -> u64 = $limbs;
+                let operand = |depth| vm.stack_peek_at(depth).and_then(|ptr| vm.bigint_limbs(ptr)).unwrap_or(0);
+
+                let cells = limbs(operand(1), operand(0)).saturating_add(1);
+                vm.gas_table().cost(self.opcode()).saturating_add(vm.gas_table().alloc_cell_cost().saturating_mul(cells))
+            }
+
+            fn disassemble(&self) -> String {
+                stringify!($name).to_lowercase()
+            }
+        }
+    };
+}
+
+impl_big_arith_inst!(BigPlus, 15, |lhs, rhs| Ok(lhs + rhs), |lhs, rhs| lhs.max(rhs).saturating_add(1),
+    "Pops two big integers off the stack and pushes their sum.");
+impl_big_arith_inst!(BigMinus, 16, |lhs, rhs| Ok(lhs - rhs), |lhs, rhs| lhs.max(rhs).saturating_add(1),
+    "Pops two big integers off the stack and pushes their difference.");
+impl_big_arith_inst!(BigMult, 17, |lhs, rhs| Ok(lhs * rhs), |lhs, rhs| lhs.saturating_add(rhs),
+    "Pops two big integers off the stack and pushes their product.");
+impl_big_arith_inst!(BigDiv, 18, |lhs, rhs| lhs / rhs, |lhs, _| lhs,
+    "Pops two big integers off the stack and pushes their quotient truncated towards zero.");
+impl_big_arith_inst!(BigRem, 19, |lhs, rhs| lhs % rhs, |lhs, rhs| lhs.min(rhs),
+    "Pops two big integers off the stack and pushes the remainder of their division.");
+
+/// # BigCmp instruction.
+///
+/// Pops two pointers to big integers off the stack and pushes the result of comparing them as
+/// a [`MikuType::I8`]: -1 if the first is less than the second, 0 if they are equal and 1 if it
+/// is greater.
+///
+/// ## Information
+/// - Opcode: 20
+/// - Operands:
+///   - None
+#[derive(Debug, PartialEq)]
+pub struct BigCmp { }
+
+impl BigCmp {
+    pub fn new() -> Self {
+        Self { }
+    }
+}
+
+impl Default for BigCmp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inst for BigCmp {
+    fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+        vm.inc_pc();
+        let rhs = vm.stack_pop()?;
+        let lhs = vm.stack_pop()?;
+        let ordering = vm.read_bigint(lhs)?.cmp(&vm.read_bigint(rhs)?);
+        vm.stack_push(MikuType::I8(ordering as i8))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        vec![0x14]
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+        if bytes.len() != 1 {
+            return Err(MikuError::BytesConversionError);
+        }
+        Ok(BigCmp::new())
+    }
+
+    fn opcode(&self) -> u8 {
+        0x14
+    }
+
+    fn disassemble(&self) -> String {
+        "bigcmp".to_string()
+    }
+}
+
+/// # FromBig instruction.
+///
+/// Pops a pointer to a big integer off the stack, converts it to the integer type with the
+/// given type identifier and pushes the result onto the stack.
+///
+/// ## Information
+/// - Opcode: 21
+/// - Operands:
+///   - type identifier ([`prim@u8`])
+#[derive(Debug, PartialEq)]
+pub struct FromBig {
+    operand: u8,
+}
+
+impl FromBig {
+    pub fn new(operand: u8) -> Self {
+        Self { operand }
+    }
+}
+
+impl Inst for FromBig {
+    fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+        vm.inc_pc();
+        let ptr = vm.stack_pop()?;
+        let value = vm.read_bigint(ptr)?.to_miku_type(self.operand)?;
+        vm.stack_push(value)
+    }
+
+    /// # Example
+    ///
+    /// ``` rust
+    /// # use vm::inst::*;
+    /// assert_eq!(vec![0x15, 0x03], FromBig::new(0x03).encode());
+    /// ```
+    fn encode(&self) -> Vec<u8> {
+        vec![0x15, self.operand]
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+        if bytes.len() != 2 {
+            return Err(MikuError::BytesConversionError);
+        }
+        Ok(FromBig::new(bytes[1]))
+    }
+
+    fn opcode(&self) -> u8 {
+        0x15
+    }
+
+    fn disassemble(&self) -> String {
+        format!("frombig {}", MikuType::type_name(self.operand).unwrap_or("?"))
+    }
 }
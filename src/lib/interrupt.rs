@@ -0,0 +1,53 @@
+//! Asynchronous interrupts.
+//!
+//! An [`InterruptHandle`] stops a [`crate::miku::MikuVM`] from another thread. The vm checks the
+//! handle before every instruction and stops with [`crate::error::MikuError::Interrupted`]
+//! without executing the instruction, so the state can be inspected and the run continued.
+//!
+//! ## Examples
+//! ``` rust
+//! use vm::{error::MikuError, inst::*, miku::MikuVM, types::MikuType};
+//!
+//! let mut vm = MikuVM::from_program(vec![Box::new(Push::new(MikuType::U8(1)))]);
+//! let handle = vm.interrupt_handle();
+//! std::thread::spawn(move || handle.interrupt()).join().unwrap();
+//!
+//! let error = vm.run_program().unwrap_err();
+//! assert!(matches!(error.error(), MikuError::Interrupted));
+//! assert_eq!(0, vm.pc());
+//! assert!(vm.run_program().is_ok());
+//! ```
+
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+
+/// A cloneable handle that interrupts the vm it was taken from.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle {
+    interrupted: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests the vm to stop before its next instruction.
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if an interrupt was requested and the vm hasn't stopped for it yet.
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+
+    /// Withdraws a requested interrupt.
+    pub fn clear(&self) {
+        self.interrupted.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns and clears the requested interrupt.
+    pub fn take(&self) -> bool {
+        self.interrupted.swap(false, Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,153 @@
+//! JSON export of the vm state.
+//!
+//! [`crate::miku::MikuVM::to_json`] describes the registers, stack frames, used .data cells,
+//! heap blocks and the disassembled program of a vm as a [`Json`] value so editor plugins and
+//! visualizers can read the state without parsing the [`std::fmt::Display`] output.
+//! Memory cells are written as strings in the [`crate::types::MikuType`] display format
+//! (e.g. `"u8 69"` or `"ptr heap 700"`) which keeps 128 bit integers and floats lossless and can
+//! be parsed back with [`std::str::FromStr`].
+//!
+//! ## Examples
+//! ``` rust
+//! use vm::{inst::*, json::Json, miku::MikuVM, types::MikuType};
+//!
+//! let mut vm = MikuVM::from_program(vec![Box::new(Push::new(MikuType::U8(69)))]);
+//! vm.run_program().unwrap();
+//!
+//! let state = vm.to_json();
+//! assert_eq!(Some(&Json::Number(1)), state.get("registers").and_then(|registers| registers.get("pc")));
+//! assert!(state.to_string().contains(r#""inst":"push u8 69""#));
+//! ```
+
+use std::fmt::Display;
+
+/// A JSON value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    /// The members of an object in the order they are written.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Creates an object from the given members.
+    pub fn object<const N: usize>(members: [(&str, Json); N]) -> Self {
+        Json::Object(members.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+
+    /// Returns the member of an object with the given key.
+    /// # Returns
+    /// - `None` if the value isn't an object or doesn't have the member.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements of an array.
+    /// # Returns
+    /// - `None` if the value isn't an array.
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<u64> for Json {
+    fn from(value: u64) -> Self {
+        Json::Number(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as u64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl<T: Into<Json>> FromIterator<T> for Json {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Json::Array(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Writes the value as compact JSON text.
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(value) => write_string(f, value),
+            Json::Array(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Writes a quoted and escaped JSON string.
+fn write_string(f: &mut std::fmt::Formatter<'_>, value: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
@@ -0,0 +1,196 @@
+//! Struct layouts.
+//!
+//! A [`StructLayout`] describes how a record is laid out in memory. Each field has a name,
+//! a type (the type identifier byte of a [`MikuType`]) and an offset counted in memory cells
+//! from the start of the record. Layouts are declared in the bytecode with the
+//! [`crate::inst::DefStruct`] instruction and used by [`crate::inst::FieldGet`] and
+//! [`crate::inst::FieldSet`] to type-check field accesses at runtime.
+
+use std::fmt::Display;
+
+use crate::{error::MikuError, tools, types::MikuType};
+
+/// A single named field of a [`StructLayout`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    name: String,
+    type_id: u8,
+    offset: usize,
+}
+
+impl Field {
+    pub fn new(name: &str, type_id: u8, offset: usize) -> Self {
+        Self { name: name.to_string(), type_id, offset }
+    }
+
+    /// The name of the field.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The type identifier byte of the values this field holds.
+    pub fn type_id(&self) -> u8 {
+        self.type_id
+    }
+
+    /// The offset of the field from the start of the record in memory cells.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// The layout of a record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructLayout {
+    name: String,
+    fields: Vec<Field>,
+}
+
+impl StructLayout {
+    /// Creates a new layout from the given fields.
+    ///
+    /// # Returns
+    /// - `Ok(StructLayout)` if the layout is valid.
+    /// - [`MikuError::UnknownTypeError`] if a field has an unknown type identifier.
+    /// - [`MikuError::InvalidStructLayout`] if two fields share a name or an offset, a field is
+    ///   of type [`MikuType::NULL`] or a name doesn't fit in 255 bytes.
+    pub fn new(name: &str, fields: Vec<Field>) -> Result<Self, MikuError> {
+        if name.len() > u8::MAX as usize || fields.len() > u8::MAX as usize {
+            return Err(MikuError::InvalidStructLayout(name.to_string()));
+        }
+
+        for (i, field) in fields.iter().enumerate() {
+            MikuType::get_bytes_length(field.type_id)?;
+
+            if field.type_id == MikuType::NULL.type_id() || field.name.len() > u8::MAX as usize {
+                return Err(MikuError::InvalidStructLayout(format!("{}.{}", name, field.name)));
+            }
+
+            if fields[..i].iter().any(|f| f.name == field.name || f.offset == field.offset) {
+                return Err(MikuError::InvalidStructLayout(format!("{}.{}", name, field.name)));
+            }
+        }
+
+        Ok(Self { name: name.to_string(), fields })
+    }
+
+    /// The name of the record.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The fields of the record in declaration order.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Returns the field at the given index.
+    pub fn field(&self, index: usize) -> Option<&Field> {
+        self.fields.get(index)
+    }
+
+    /// Returns the index of the field with the given name.
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|field| field.name == name)
+    }
+
+    /// The number of memory cells a record with this layout occupies.
+    pub fn size(&self) -> usize {
+        self.fields.iter().map(|field| field.offset + 1).max().unwrap_or(0)
+    }
+
+    /// Calculates the length of an encoded layout at the start of the given bytes.
+    /// # Returns
+    /// - `Ok(size of the layout)`
+    /// - [`MikuError::BytesConversionError`] if the bytes end before the layout does.
+    pub fn get_bytes_length(bytes: &[u8]) -> Result<usize, MikuError> {
+        let name_length = *bytes.first().ok_or(MikuError::BytesConversionError)? as usize;
+        let field_count = *bytes.get(name_length + 1).ok_or(MikuError::BytesConversionError)? as usize;
+        let mut length = name_length + 2;
+
+        for _ in 0..field_count {
+            let field_name_length = *bytes.get(length).ok_or(MikuError::BytesConversionError)? as usize;
+            length += field_name_length + 2 + size_of::<usize>();
+        }
+
+        if length > bytes.len() {
+            return Err(MikuError::BytesConversionError);
+        }
+
+        Ok(length)
+    }
+}
+
+/// Takes a [StructLayout] and turns it into a vector ([Vec]) of bytes.
+///
+/// # Encoding format
+/// - name length (1 byte) followed by the name.
+/// - field count (1 byte).
+/// - for each field: name length (1 byte), name, type identifier (1 byte), offset ([`prim@usize`]).
+impl From<&StructLayout> for Vec<u8> {
+    fn from(value: &StructLayout) -> Self {
+        let mut bytes = vec![value.name.len() as u8];
+        bytes.extend(value.name.as_bytes());
+        bytes.push(value.fields.len() as u8);
+
+        for field in &value.fields {
+            bytes.push(field.name.len() as u8);
+            bytes.extend(field.name.as_bytes());
+            bytes.push(field.type_id);
+            bytes.extend(field.offset.to_le_bytes());
+        }
+
+        bytes
+    }
+}
+
+/// Takes a slice of bytes ([u8]) and turns them into a [StructLayout].
+/// ### Results in
+/// - [`StructLayout`]
+/// - [`MikuError::BytesConversionError`] if the bytes are not a valid encoding.
+/// - Any error of [`StructLayout::new`] if the decoded layout is invalid.
+impl TryFrom<&[u8]> for StructLayout {
+    type Error = MikuError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if StructLayout::get_bytes_length(value)? != value.len() {
+            return Err(MikuError::BytesConversionError);
+        }
+
+        let name_length = value[0] as usize;
+        let name = read_name(&value[1..name_length + 1])?;
+        let field_count = value[name_length + 1] as usize;
+        let mut cursor = name_length + 2;
+        let mut fields = Vec::with_capacity(field_count);
+
+        for _ in 0..field_count {
+            let field_name_length = value[cursor] as usize;
+            let field_name = read_name(&value[cursor + 1..cursor + 1 + field_name_length])?;
+            cursor += field_name_length + 1;
+            let type_id = value[cursor];
+            let offset = usize::from_le_bytes(tools::convert_bytes(&value[cursor + 1..cursor + 1 + size_of::<usize>()])?);
+            cursor += 1 + size_of::<usize>();
+            fields.push(Field::new(&field_name, type_id, offset));
+        }
+
+        StructLayout::new(&name, fields)
+    }
+}
+
+/// Formats a [StructLayout] as `<name> { <field>: <type> @ <offset>, ... }`,
+/// e.g. `point { x: i32 @ 0, y: i32 @ 1 }`.
+impl Display for StructLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {{", self.name)?;
+        for (i, field) in self.fields.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            let type_name = MikuType::type_name(field.type_id).unwrap_or("?");
+            write!(f, "{}{}: {} @ {}", separator, field.name, type_name, field.offset)?;
+        }
+        write!(f, " }}")
+    }
+}
+
+fn read_name(bytes: &[u8]) -> Result<String, MikuError> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| MikuError::BytesConversionError)
+}
@@ -0,0 +1,104 @@
+//! Byte-addressed memory.
+//!
+//! The main memory of [`crate::miku::MikuVM`] is an array of [`MikuType`] cells, so every cell
+//! holds one tagged value no matter its size. [`ByteMemory`] is a plain array of bytes instead,
+//! where numeric values are stored at byte offsets in their little endian encoding (the same
+//! encoding [`MikuType`] uses without the type identifier byte). This lets programs build packed
+//! binary structures.
+//!
+//! Programs address the byte memory of a vm with pointers into [`crate::types::Segment::Bytes`]
+//! that hold byte offsets. Accesses through them honour the segment's permissions, watchpoints
+//! and mapped devices like accesses of the cell memory do.
+
+use crate::{error::MikuError, types::MikuType};
+
+/// A byte-addressed memory region.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ByteMemory {
+    bytes: Vec<u8>,
+}
+
+impl ByteMemory {
+    /// Creates a zeroed byte memory of the given size.
+    pub fn new(size: usize) -> Self {
+        Self { bytes: vec![0; size] }
+    }
+
+    /// The size of the memory in bytes.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns true if the memory has no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The raw bytes of the memory.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Loads a value of the given type from the given offset.
+    ///
+    /// # Returns
+    /// - `Ok(MikuType)` on successful load.
+    /// - [`MikuError::UnsupportedTypeError`] if the type isn't numeric.
+    /// - [`MikuError::MisalignedAccess`] if the offset isn't a multiple of the type's width.
+    /// - [`MikuError::SegmentationFault`] if the value doesn't fit within the memory.
+    pub fn load(&self, offset: usize, type_id: u8) -> Result<MikuType, MikuError> {
+        let range = self.access_range(offset, type_id)?;
+
+        let mut encoded = vec![type_id];
+        encoded.extend(&self.bytes[range]);
+        MikuType::try_from(&encoded[..])
+    }
+
+    /// Stores a value at the given offset using as many bytes as the value's type is wide.
+    ///
+    /// # Returns
+    /// - `Ok(())` on successful store.
+    /// - [`MikuError::UnsupportedTypeError`] if the value isn't numeric.
+    /// - [`MikuError::MisalignedAccess`] if the offset isn't a multiple of the value's width.
+    /// - [`MikuError::SegmentationFault`] if the value doesn't fit within the memory.
+    pub fn store(&mut self, offset: usize, value: MikuType) -> Result<(), MikuError> {
+        let range = self.access_range(offset, value.type_id())?;
+
+        let encoded = Vec::from(value);
+        self.bytes[range].copy_from_slice(&encoded[1..]);
+        Ok(())
+    }
+
+    /// The width in bytes of the values of a type that can be stored in byte memory.
+    pub fn width(type_id: u8) -> Result<usize, MikuError> {
+        match type_id {
+            0x00..=0x09 | 0x0C | 0x0D => Ok(MikuType::get_bytes_length(type_id)? - 1),
+            _ => Err(MikuError::UnsupportedTypeError(type_id)),
+        }
+    }
+
+    /// Checks an access of the given type at the given offset and returns the accessed bytes.
+    ///
+    /// # Returns
+    /// - `Ok(Range<usize>)` the offsets of the accessed bytes.
+    /// - The errors of [`ByteMemory::load`].
+    pub fn access_range(&self, offset: usize, type_id: u8) -> Result<std::ops::Range<usize>, MikuError> {
+        let width = Self::width(type_id)?;
+
+        if !offset.is_multiple_of(width) {
+            return Err(MikuError::MisalignedAccess(width, offset));
+        }
+
+        match offset.checked_add(width) {
+            Some(end) if end <= self.bytes.len() => Ok(offset..end),
+            _ => Err(MikuError::SegmentationFault),
+        }
+    }
+}
+
+/// Creates a byte memory holding the given bytes.
+impl From<Vec<u8>> for ByteMemory {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+}
@@ -12,28 +12,63 @@
 //! };
 //!
 //! let mut vm = MikuVM::new();
-//! let inst1: Box<dyn Inst> = Box::new(Push::new(MikuType::U8(69)));
-//! vm.push_inst(&inst1);
+//! vm.push_inst(Box::new(Push::new(MikuType::U8(69))));
 //! let _ = vm.run_program();
+//!
+//! let mut vm = MikuVM::new();
+//! vm.load_program(vec![Box::new(Push::new(MikuType::U8(1))), Box::new(Pop::new())]);
+//! assert!(vm.run_program().is_ok());
 //! ```
 
 use crate::{
-    error::MikuError, inst::*, types::MikuType, DATA_END, DATA_START, HEAP_END, HEAP_START, MEMORY_SIZE, STACK_END, STACK_START};
-use std::{fmt::Display, usize};
+    bigint::BigInt, config::{MemoryLayout, VmConfig, VmConfigBuilder}, debug::{Debugger, Pause, WatchKind, Watchpoint}, error::{MikuError, RuntimeError}, gas::GasTable, heap::{GcStats, Heap}, inst::*, interrupt::InterruptHandle, json::Json, layout::StructLayout, memory::ByteMemory, mmio::{Device, MappedDevice}, observer::Observer, program::{InstRegistry, Program}, snapshot::{SnapshotReader, SnapshotWriter}, trap::{TrapAction, TrapHandler, TrapKind}, types::{Access, MikuType, Pointer, Promotion, Segment}};
+use std::{collections::HashMap, fmt::Display, ops::Range};
+
+/// The state of the vm after executing instructions.
+#[derive(Debug)]
+pub enum Status {
+    /// There are instructions left to execute.
+    Running,
+    /// The program has terminated.
+    Halted,
+    /// An instruction failed with the given error.
+    Trapped(RuntimeError),
+    /// A breakpoint or a watchpoint fired.
+    Paused(Pause),
+}
+
+impl Status {
+    /// Returns true if the vm can keep executing instructions.
+    pub fn is_running(&self) -> bool {
+        matches!(self, Status::Running)
+    }
+}
+
+/// A frame of the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackFrame {
+    /// The program counter of the instruction the frame was executing.
+    pub pc: usize,
+    /// The address of the first entry of the frame.
+    pub base: usize,
+    /// The address after the last entry of the frame.
+    pub top: usize,
+}
 
 /// The main structure of the virtual machine.
 #[derive(Debug)]
-pub struct MikuVM<'a> {
+pub struct MikuVM {
     /// Points to the top of the current stackframe.
     stack_top: usize,
     /// Points to the base of the current stackframe.
     stack_base: usize,
     
     /// The RAM.
-    /// An array of [`MikuType`].
-    /// First 40% of it is the .data section reserved for constants.
-    /// The rest of the memory is the heap.
-    memory: [MikuType; MEMORY_SIZE],
+    /// A vector of [`MikuType`] split into the stack, the .data section reserved for constants
+    /// and the heap as described by `layout`.
+    memory: Vec<MikuType>,
+    /// Where each segment of the memory starts and ends.
+    layout: MemoryLayout,
     /// This points to the largest address where a non NULL value is stored in the .data section of
     /// the memory.
     largest_data_address: usize,
@@ -41,56 +76,299 @@ pub struct MikuVM<'a> {
     /// the memory.
     largest_heap_address: usize,
 
+    /// The allocator of the heap segment.
+    heap: Heap,
+    /// The access permissions of the segments. Segments that aren't listed are
+    /// [`Access::ReadWrite`].
+    permissions: HashMap<Segment, Access>,
+    /// The devices mapped into the memory.
+    devices: Vec<MappedDevice>,
+    /// The devices mapped into the byte memory.
+    byte_devices: Vec<MappedDevice>,
+    /// Set once the initialisation phase of the program is over and the .data segment is sealed.
+    initialised: bool,
+
+    /// The byte-addressed memory.
+    /// Empty until it is enabled with [`MikuVM::set_byte_memory`].
+    byte_memory: ByteMemory,
+
+    /// The struct layouts declared by the program keyed by their id.
+    structs: HashMap<usize, StructLayout>,
+
+    /// The policy used by the arithmetic instructions to reconcile mismatched operand types.
+    promotion: Promotion,
+
+    /// The breakpoints and watchpoints.
+    debugger: Debugger,
+    /// The observers notified about the execution.
+    observers: Vec<Box<dyn Observer>>,
+
+    /// The remaining gas. `None` if execution isn't metered.
+    gas: Option<u64>,
+    /// The gas costs of the instructions.
+    gas_table: GasTable,
+
+    /// The first memory address the current instruction failed to access.
+    fault_address: Option<usize>,
+    /// The handlers called when an instruction fails, keyed by the kind of error they handle.
+    trap_handlers: HashMap<TrapKind, Box<dyn TrapHandler>>,
+    /// Checked before every instruction to stop the vm from another thread.
+    interrupt: InterruptHandle,
+
     /// The loaded program.
-    /// A [`Vec`] of `&'a Box<dyn Inst>` (a reference with lifetime a to a pointer that points to an object that implements the [Inst] trait)
-    program: Vec<&'a Box<dyn Inst>>,
+    /// A [`Program`] of pointers to objects that implement the [Inst] trait, possibly shared
+    /// with other vms.
+    program: Program,
     /// The program counter.
     /// Points to the next instruciton to be executed.
     pc: usize,
 }
 
-impl<'a> MikuVM<'a> {
-    /// Creates a new empty vm.
+impl MikuVM {
+    /// Creates a new empty vm with the default configuration.
     pub fn new() -> Self {
+        Self::with_config(VmConfig::default())
+    }
+
+    /// Creates a vm with the default configuration that owns the given program.
+    pub fn from_program(program: Vec<Box<dyn Inst>>) -> Self {
+        let mut vm = Self::new();
+        vm.load_program(program);
+        vm
+    }
+
+    /// Creates a new empty vm with the given configuration.
+    pub fn with_config(config: VmConfig) -> Self {
+        let layout = config.layout().clone();
+
         Self { 
-            stack_top: STACK_START, 
-            stack_base: STACK_START,
-            memory: [MikuType::NULL; MEMORY_SIZE],
-            largest_data_address: DATA_START,
-            largest_heap_address: HEAP_START,
-            program: Vec::new(), 
+            stack_top: layout.segment(Segment::Stack).start, 
+            stack_base: layout.segment(Segment::Stack).start,
+            memory: vec![MikuType::NULL; layout.memory_size()],
+            largest_data_address: layout.segment(Segment::Data).start,
+            largest_heap_address: layout.segment(Segment::Heap).start,
+            heap: Heap::new(layout.segment(Segment::Heap), config.gc_threshold()),
+            permissions: HashMap::new(),
+            devices: Vec::new(),
+            byte_devices: Vec::new(),
+            initialised: false,
+            layout,
+            byte_memory: ByteMemory::new(config.byte_memory_size()),
+            structs: HashMap::new(),
+            promotion: Promotion::Strict,
+            debugger: Debugger::new(),
+            observers: Vec::new(),
+            gas: config.gas_limit(),
+            gas_table: GasTable::default(),
+            fault_address: None,
+            trap_handlers: HashMap::new(),
+            interrupt: InterruptHandle::new(),
+            program: Program::default(),
             pc: 0 
         }
     }
     
     /// Runs until the program terminates. Executes each instruction stored in program.
+    /// The .data segment is sealed as soon as the first instruction that isn't part of the
+    /// initialisation phase (see [`Inst::initialises_data`]) is executed.
+    /// Breakpoints and watchpoints are ignored, use [`MikuVM::run`] to stop at them.
     /// # Returns
     /// - `Ok(())` if the execution doesn't hit an error.
-    /// - [`MikuError`] if something goes wrong during execution.
-    pub fn run_program(&mut self) -> Result<(), MikuError> {
-        while self.pc != self.program.len() {
-            let inst = self.program[self.pc];
-            inst.execute(self)?;
+    /// - [`RuntimeError`] if something goes wrong during execution. It records the failed
+    ///   instruction, the faulting address and a backtrace next to the [`MikuError`].
+    pub fn run_program(&mut self) -> Result<(), RuntimeError> {
+        loop {
+            match self.step() {
+                Status::Running | Status::Paused(_) => {}
+                Status::Halted => return Ok(()),
+                Status::Trapped(error) => return Err(error),
+            }
         }
+    }
 
-        Ok(())
+    /// Runs until the program terminates, fails or pauses.
+    /// # Returns
+    /// - [`Status::Halted`], [`Status::Trapped`] or [`Status::Paused`].
+    pub fn run(&mut self) -> Status {
+        loop {
+            match self.step() {
+                Status::Running => {}
+                status => return status,
+            }
+        }
     }
-    
+
+    /// Executes the instruction the program counter points to.
+    /// # Returns
+    /// - [`Status::Running`] if there are instructions left to execute.
+    /// - [`Status::Halted`] if the program has terminated.
+    /// - [`Status::Trapped`] if the instruction failed or there isn't enough gas to execute it.
+    ///   Running out of gas doesn't execute the instruction so it can be retried after the gas
+    ///   is topped up. If a trap handler is registered for the error it decides whether the
+    ///   vm keeps running instead, see [`crate::trap`]. An interrupt requested through an
+    ///   [`InterruptHandle`] traps with [`MikuError::Interrupted`] before the instruction is
+    ///   executed and isn't passed to the trap handlers.
+    /// - [`Status::Paused`] if the instruction has a breakpoint, in which case it is executed by
+    ///   the next step, or if it accessed a watched address.
+    pub fn step(&mut self) -> Status {
+        if self.pc >= self.program.len() {
+            // An interrupt that arrives after the program terminated has nothing left to stop.
+            self.interrupt.clear();
+            return Status::Halted;
+        }
+        if self.interrupt.take() {
+            let error = MikuError::Interrupted;
+            return Status::Trapped(RuntimeError::new(error, self.pc, self.program[self.pc].disassemble(), None, self.frames()));
+        }
+        if let Some(pause) = self.debugger.check_breakpoint(self.pc) {
+            return Status::Paused(pause);
+        }
+
+        // Accesses made by the host between steps don't fire watchpoints or count as faults.
+        self.debugger.take_hit();
+        self.fault_address = None;
+
+        // The program is cloned while the instruction runs so it can borrow the vm mutably.
+        let program = self.program.clone();
+        let pc = self.pc;
+        let stack_top = self.stack_top;
+        let inst = &program[pc];
+        self.notify(|observer| observer.before_inst(pc, inst.as_ref()));
+        let result = match self.gas {
+            Some(_) => self.charge_gas(inst.gas_cost(self)),
+            None => Ok(()),
+        };
+        let result = result.and_then(|_| {
+            if !self.initialised && !inst.initialises_data() {
+                self.seal_data();
+            }
+            inst.execute(self)
+        });
+        match &result {
+            Ok(()) => self.notify(|observer| observer.after_inst(pc, inst.as_ref())),
+            Err(error) => self.notify(|observer| observer.on_error(pc, error)),
+        }
+        let hit = self.debugger.take_hit();
+
+        match (result, hit) {
+            (Err(error), _) => {
+                let address = self.fault_address.take();
+                let backtrace = self.backtrace(pc);
+                self.trap(RuntimeError::new(error, pc, self.program[pc].disassemble(), address, backtrace), stack_top)
+            }
+            (Ok(()), Some(pause)) => Status::Paused(pause),
+            (Ok(()), None) if self.pc >= self.program.len() => Status::Halted,
+            (Ok(()), None) => Status::Running,
+        }
+    }
+
+    /// Calls the trap handler registered for the error. The program counter and the top of the
+    /// stack are rewound to the given values first.
+    fn trap(&mut self, error: RuntimeError, stack_top: usize) -> Status {
+        let kind = TrapKind::from(error.error());
+        let Some(mut handler) = self.trap_handlers.remove(&kind) else {
+            return Status::Trapped(error);
+        };
+
+        self.pc = error.pc();
+        self.stack_top = stack_top;
+        let action = handler.handle(self, &error);
+        // A handler registered by the handler itself replaces it.
+        self.trap_handlers.entry(kind).or_insert(handler);
+
+        match action {
+            TrapAction::Resume if self.pc >= self.program.len() => Status::Halted,
+            TrapAction::Resume => Status::Running,
+            TrapAction::Skip => {
+                self.pc = error.pc() + 1;
+                match self.pc >= self.program.len() {
+                    true => Status::Halted,
+                    false => Status::Running,
+                }
+            }
+            TrapAction::Abort => Status::Trapped(error),
+        }
+    }
+
+    /// Returns a handle that interrupts the vm from another thread. See [`crate::interrupt`].
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Registers a handler that is called when an instruction fails with an error of the given
+    /// kind. See [`crate::trap`].
+    /// # Returns
+    /// - `Some(Box<dyn TrapHandler>)` the handler that was registered for the kind before.
+    pub fn set_trap_handler(&mut self, kind: TrapKind, handler: Box<dyn TrapHandler>) -> Option<Box<dyn TrapHandler>> {
+        self.trap_handlers.insert(kind, handler)
+    }
+
+    /// Removes the handler registered for the given kind of error.
+    pub fn remove_trap_handler(&mut self, kind: TrapKind) -> Option<Box<dyn TrapHandler>> {
+        self.trap_handlers.remove(&kind)
+    }
+
+    /// Executes at most the given number of instructions.
+    /// # Returns
+    /// - [`Status::Running`] if the program is still running after the instructions.
+    /// - [`Status::Halted`], [`Status::Trapped`] or [`Status::Paused`] if the program stopped
+    ///   earlier.
+    pub fn run_for(&mut self, steps: usize) -> Status {
+        for _ in 0..steps {
+            match self.step() {
+                Status::Running => {}
+                status => return status,
+            }
+        }
+
+        match self.pc >= self.program.len() {
+            true => Status::Halted,
+            false => Status::Running,
+        }
+    }
+
+    /// Executes instructions until the program counter reaches the given value.
+    /// At least one instruction is executed.
+    /// # Returns
+    /// - [`Status::Running`] if the program counter reached the value.
+    /// - [`Status::Halted`], [`Status::Trapped`] or [`Status::Paused`] if the program stopped
+    ///   before that.
+    pub fn run_until(&mut self, pc: usize) -> Status {
+        loop {
+            match self.step() {
+                Status::Running if self.pc == pc => return Status::Running,
+                Status::Running => {}
+                status => return status,
+            }
+        }
+    }
+
     /// Write data in the .data section of the RAM.
     /// 
     /// # Returns
     /// - `Ok(())` if the data was successfully stored.
     /// - [`MikuError::UsedDataSpace`] if the .data section isn't [`MikuType::NULL`] at the given
-    /// address.
+    ///   address.
     /// - [`MikuError::SegmentationFault`] if the given address is outside of the .data section's
-    /// bounds or if the address is larger than the [`MEMORY_SIZE`].
+    ///   bounds.
+    /// - [`MikuError::ProtectionFault`] if the .data section is read-only.
     pub fn define_data(&mut self, data: MikuType, address: usize) -> Result<(), MikuError> {
-        if address < DATA_START || address > DATA_END || address >= MEMORY_SIZE {
+        self.faulting_at(Some(address), |vm| vm.define_data_at(data, address))
+    }
+
+    fn define_data_at(&mut self, data: MikuType, address: usize) -> Result<(), MikuError> {
+        if !self.layout.segment(Segment::Data).contains(&address) {
             return Err(MikuError::SegmentationFault);
         }
+        self.check_writable(Segment::Data)?;
+        self.debugger.on_access(address..address + 1, WatchKind::Write);
+
+        if let Some(device) = self.device_at(address) {
+            return device.write(address, data);
+        }
         
         match self.memory[address] {
-            MikuType::NULL => self.memory[address] = data,
+            MikuType::NULL => self.write_cell(address, data),
             _ => return Err(MikuError::UsedDataSpace),
         }
 
@@ -107,20 +385,516 @@ impl<'a> MikuVM<'a> {
     ///
     /// # Returns
     /// - `Ok(MikuType)` on successful read.
-    /// - [`MikuError::SegmentationFault`] if the address is out of bounds or the pointer is [`MikuType::NULL`].
-    /// - [`MikuError::InvalidPointerType`] if the pointer isn't a [`MikuType::U64`].
-    pub fn deref_ptr(&self, ptr: MikuType) -> Result<MikuType, MikuError> {
-        let address: usize = match ptr {
-            MikuType::U64(address) => address as usize,
+    /// - [`MikuError::SegmentationFault`] if the address is outside of the pointer's segment or the pointer is [`MikuType::NULL`].
+    /// - [`MikuError::InvalidPointerType`] if the pointer isn't a [`MikuType::Ptr`].
+    /// - Any error of the [`Device`] if the address is mapped to one.
+    pub fn deref_ptr(&mut self, ptr: MikuType) -> Result<MikuType, MikuError> {
+        self.faulting_at(Self::ptr_address(ptr), |vm| {
+            let address = vm.resolve_ptr(ptr)?;
+            vm.debugger.on_access(address..address + 1, WatchKind::Read);
+
+            match vm.device_at(address) {
+                Some(device) => device.read(address),
+                None => Ok(vm.memory[address]),
+            }
+        })
+    }
+
+    /// Write the given data to the memory at the given address.
+    ///
+    /// # Returns
+    /// - `Ok(())` on successful write.
+    /// - [`MikuError::SegmentationFault`] if the address is outside of the pointer's segment or the pointer is [`MikuType::NULL`].
+    /// - [`MikuError::InvalidPointerType`] if the pointer isn't a [`MikuType::Ptr`].
+    /// - [`MikuError::ProtectionFault`] if the pointer's segment is read-only.
+    /// - Any error of the [`Device`] if the address is mapped to one.
+    pub fn write_ptr(&mut self, ptr: MikuType, data: MikuType) -> Result<(), MikuError> {
+        self.faulting_at(Self::ptr_address(ptr), |vm| vm.write_ptr_at(ptr, data))
+    }
+
+    fn write_ptr_at(&mut self, ptr: MikuType, data: MikuType) -> Result<(), MikuError> {
+        let address = self.resolve_ptr(ptr)?;
+        if let MikuType::Ptr(pointer) = ptr {
+            self.check_writable(pointer.segment())?;
+        }
+        self.debugger.on_access(address..address + 1, WatchKind::Write);
+
+        if let Some(device) = self.device_at(address) {
+            return device.write(address, data);
+        }
+        self.write_cell(address, data);
+
+        match ptr {
+            MikuType::Ptr(pointer) if pointer.segment() == Segment::Data && address > self.largest_data_address => {
+                self.largest_data_address = address;
+            }
+            MikuType::Ptr(pointer) if pointer.segment() == Segment::Heap && address > self.largest_heap_address => {
+                self.largest_heap_address = address;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Moves a pointer by the given number of cells, or bytes for pointers into the byte memory.
+    /// The resulting pointer has to stay within the segment of the original pointer.
+    ///
+    /// # Returns
+    /// - `Ok(MikuType::Ptr)` the moved pointer.
+    /// - [`MikuError::SegmentationFault`] if the result is outside of the pointer's segment or the pointer is [`MikuType::NULL`].
+    /// - [`MikuError::InvalidPointerType`] if the pointer isn't a [`MikuType::Ptr`].
+    pub fn offset_ptr(&self, ptr: MikuType, offset: i64) -> Result<MikuType, MikuError> {
+        let pointer = match ptr {
+            MikuType::Ptr(pointer) => pointer,
             MikuType::NULL => return Err(MikuError::SegmentationFault),
             _ => return Err(MikuError::InvalidPointerType(ptr)),
         };
 
-        if address >= MEMORY_SIZE {
+        let address = pointer.address()
+            .checked_add_signed(offset)
+            .ok_or(MikuError::SegmentationFault)?;
+        let moved = MikuType::Ptr(Pointer::new(pointer.segment(), address));
+        if pointer.segment() != Segment::Bytes {
+            self.resolve_ptr(moved)?;
+        } else if !usize::try_from(address).is_ok_and(|offset| offset < self.byte_memory.len()) {
             return Err(MikuError::SegmentationFault);
         }
+        Ok(moved)
+    }
 
-        Ok(self.memory[address])
+    /// The memory layout of the vm.
+    pub fn layout(&self) -> &MemoryLayout {
+        &self.layout
+    }
+
+    /// The access permissions of the given segment.
+    pub fn access(&self, segment: Segment) -> Access {
+        self.permissions.get(&segment).copied().unwrap_or_default()
+    }
+
+    /// Sets the access permissions of the given segment.
+    pub fn set_access(&mut self, segment: Segment, access: Access) {
+        self.permissions.insert(segment, access);
+    }
+
+    /// Ends the initialisation phase and makes the .data segment read-only.
+    /// [`MikuVM::run_program()`] calls this automatically.
+    pub fn seal_data(&mut self) {
+        self.initialised = true;
+        self.set_access(Segment::Data, Access::ReadOnly);
+    }
+
+    /// Maps a device onto the given range of addresses. Reads and writes of those addresses are
+    /// routed to the device instead of the memory. Mapped heap addresses are never allocated.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the device was mapped.
+    /// - [`MikuError::InvalidMapping`] if the range is empty, isn't within the .data or the heap
+    ///   segment or overlaps another device or an allocated heap block.
+    pub fn map_device(&mut self, range: Range<usize>, device: Box<dyn Device>) -> Result<(), MikuError> {
+        let overlaps = self.devices.iter().any(|mapped| mapped.range().start < range.end && range.start < mapped.range().end);
+        let segment = match self.layout.segment_of(range.start) {
+            Some(segment) if segment != Segment::Stack && !range.is_empty() && range.end <= self.layout.segment(segment).end && !overlaps => segment,
+            _ => return Err(MikuError::InvalidMapping(range.start, range.end)),
+        };
+
+        if segment == Segment::Heap {
+            self.heap.reserve(range.clone())?;
+        }
+
+        self.devices.push(MappedDevice::new(range, device));
+        Ok(())
+    }
+
+    /// Removes the device mapped onto the given address.
+    ///
+    /// # Returns
+    /// - `Some(Box<dyn Device>)` the removed device.
+    /// - `None` if no device is mapped onto the address.
+    pub fn unmap_device(&mut self, address: usize) -> Option<Box<dyn Device>> {
+        let index = self.devices.iter().position(|mapped| mapped.contains(address))?;
+        let mapped = self.devices.remove(index);
+        self.heap.release(mapped.range().start);
+        Some(mapped.into_device())
+    }
+
+    /// Maps a device onto the given range of byte memory offsets. Loads and stores that start in
+    /// that range are routed to the device, which has to return values of the loaded type.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the device was mapped.
+    /// - [`MikuError::InvalidMapping`] if the range is empty, isn't within the byte memory or
+    ///   overlaps another device.
+    pub fn map_byte_device(&mut self, range: Range<usize>, device: Box<dyn Device>) -> Result<(), MikuError> {
+        let overlaps = self.byte_devices.iter().any(|mapped| mapped.range().start < range.end && range.start < mapped.range().end);
+        if range.is_empty() || range.end > self.byte_memory.len() || overlaps {
+            return Err(MikuError::InvalidMapping(range.start, range.end));
+        }
+
+        self.byte_devices.push(MappedDevice::new(range, device));
+        Ok(())
+    }
+
+    /// Removes the device mapped onto the given byte memory offset.
+    ///
+    /// # Returns
+    /// - `Some(Box<dyn Device>)` the removed device.
+    /// - `None` if no device is mapped onto the offset.
+    pub fn unmap_byte_device(&mut self, offset: usize) -> Option<Box<dyn Device>> {
+        let index = self.byte_devices.iter().position(|mapped| mapped.contains(offset))?;
+        Some(self.byte_devices.remove(index).into_device())
+    }
+
+    /// Registers an observer that is notified about the execution.
+    pub fn add_observer(&mut self, observer: Box<dyn Observer>) {
+        self.observers.push(observer);
+    }
+
+    /// Removes all observers and returns them.
+    pub fn take_observers(&mut self) -> Vec<Box<dyn Observer>> {
+        std::mem::take(&mut self.observers)
+    }
+
+    /// Calls the given callback on every observer.
+    fn notify(&mut self, mut callback: impl FnMut(&mut dyn Observer)) {
+        for observer in &mut self.observers {
+            callback(observer.as_mut());
+        }
+    }
+
+    /// Runs a memory access and records the given address as the faulting address of the current
+    /// instruction if the access fails. Only the first fault of an instruction is recorded.
+    fn faulting_at<T>(&mut self, address: Option<usize>, access: impl FnOnce(&mut Self) -> Result<T, MikuError>) -> Result<T, MikuError> {
+        let result = access(self);
+        if result.is_err() && self.fault_address.is_none() {
+            self.fault_address = address;
+        }
+        result
+    }
+
+    /// The address a pointer points to, saturated to [`usize::MAX`].
+    fn ptr_address(ptr: MikuType) -> Option<usize> {
+        match ptr {
+            MikuType::Ptr(pointer) => Some(usize::try_from(pointer.address()).unwrap_or(usize::MAX)),
+            _ => None,
+        }
+    }
+
+    /// Writes a memory cell and notifies the observers.
+    fn write_cell(&mut self, address: usize, value: MikuType) {
+        if !self.observers.is_empty() {
+            let old = self.memory[address];
+            self.notify(|observer| observer.on_memory_write(address, old, value));
+        }
+        self.memory[address] = value;
+    }
+
+    /// Returns the device mapped onto the given address.
+    fn device_at(&mut self, address: usize) -> Option<&mut MappedDevice> {
+        self.devices.iter_mut().find(|mapped| mapped.contains(address))
+    }
+
+    /// Returns [`MikuError::ProtectionFault`] if the given segment is read-only.
+    fn check_writable(&self, segment: Segment) -> Result<(), MikuError> {
+        match self.access(segment) {
+            Access::ReadWrite => Ok(()),
+            Access::ReadOnly => Err(MikuError::ProtectionFault(segment)),
+        }
+    }
+
+    /// Turns a pointer into a memory address checking that it is within the pointer's segment.
+    fn resolve_ptr(&self, ptr: MikuType) -> Result<usize, MikuError> {
+        let pointer = match ptr {
+            MikuType::Ptr(pointer) => pointer,
+            MikuType::NULL => return Err(MikuError::SegmentationFault),
+            _ => return Err(MikuError::InvalidPointerType(ptr)),
+        };
+
+        let address = usize::try_from(pointer.address()).map_err(|_| MikuError::SegmentationFault)?;
+        if !self.layout.segment(pointer.segment()).contains(&address) {
+            return Err(MikuError::SegmentationFault);
+        }
+
+        Ok(address)
+    }
+
+    /// Allocates a block of the given number of cells on the heap.
+    /// If automatic garbage collection is enabled and the heap is under pressure, garbage is
+    /// collected first.
+    ///
+    /// # Returns
+    /// - `Ok(MikuType::Ptr)` a pointer to the start of the block.
+    /// - [`MikuError::OutOfMemory`] if the heap has no free space large enough.
+    /// - [`MikuError::ProtectionFault`] if the heap is read-only.
+    pub fn alloc(&mut self, size: usize) -> Result<MikuType, MikuError> {
+        self.check_writable(Segment::Heap)?;
+        if self.heap.needs_collection(size) {
+            self.collect_garbage();
+        }
+
+        let address = self.heap.alloc(size)?;
+        let block = self.heap.block_of(address).unwrap_or(address..address);
+        self.debugger.on_access(block.clone(), WatchKind::Write);
+        block.for_each(|address| self.write_cell(address, MikuType::NULL));
+
+        Ok(MikuType::Ptr(Pointer::new(Segment::Heap, address as u64)))
+    }
+
+    /// Frees the heap block the pointer points to and resets its cells to [`MikuType::NULL`].
+    ///
+    /// # Returns
+    /// - `Ok(())` if the block was freed.
+    /// - [`MikuError::InvalidFree`] if the pointer doesn't point to the start of a block.
+    /// - [`MikuError::InvalidPointerType`] if the pointer isn't a [`MikuType::Ptr`].
+    /// - [`MikuError::ProtectionFault`] if the heap is read-only.
+    pub fn free(&mut self, ptr: MikuType) -> Result<(), MikuError> {
+        self.faulting_at(Self::ptr_address(ptr), |vm| vm.free_at(ptr))
+    }
+
+    fn free_at(&mut self, ptr: MikuType) -> Result<(), MikuError> {
+        let pointer = match ptr {
+            MikuType::Ptr(pointer) => pointer,
+            _ => return Err(MikuError::InvalidPointerType(ptr)),
+        };
+
+        let address = usize::try_from(pointer.address()).map_err(|_| MikuError::SegmentationFault)?;
+        if pointer.segment() != Segment::Heap {
+            return Err(MikuError::InvalidFree(address));
+        }
+        self.check_writable(Segment::Heap)?;
+
+        let block = self.heap.free(address)?;
+        self.debugger.on_access(block.clone(), WatchKind::Write);
+        block.for_each(|address| self.write_cell(address, MikuType::NULL));
+        Ok(())
+    }
+
+    /// Runs the mark-and-sweep garbage collector over the heap segment.
+    /// The stack entries and the .data cells are the roots, every heap block that can't be
+    /// reached from them through pointers is freed. The cells of the freed blocks are reset like
+    /// [`MikuVM::free`] does, so the writes reach watchpoints and observers.
+    ///
+    /// # Returns
+    /// - The number of freed blocks.
+    pub fn collect_garbage(&mut self) -> usize {
+        let stack = self.layout.segment(Segment::Stack).start..self.stack_top;
+        let roots: Vec<MikuType> = self.memory[stack].iter()
+            .chain(&self.memory[self.layout.segment(Segment::Data)])
+            .copied()
+            .collect();
+
+        let freed = self.heap.collect(&self.memory, roots.into_iter());
+        for block in &freed {
+            self.debugger.on_access(block.clone(), WatchKind::Write);
+            block.clone().for_each(|address| self.write_cell(address, MikuType::NULL));
+        }
+        freed.len()
+    }
+
+    /// Allocates a big integer on the heap. The block holds the signed number of limbs as a
+    /// [`MikuType::I64`] followed by the limbs as [`MikuType::U32`]s, least significant first.
+    ///
+    /// # Returns
+    /// - `Ok(MikuType::Ptr)` a pointer to the start of the block.
+    /// - Any error of [`MikuVM::alloc`] or [`MikuVM::write_ptr`].
+    pub fn alloc_bigint(&mut self, value: &BigInt) -> Result<MikuType, MikuError> {
+        let limbs = value.limbs();
+        let ptr = self.alloc(limbs.len() + 1)?;
+
+        let len = limbs.len() as i64;
+        self.write_ptr(ptr, MikuType::I64(if value.is_negative() { -len } else { len }))?;
+        for (i, limb) in limbs.iter().enumerate() {
+            self.write_ptr(self.offset_ptr(ptr, i as i64 + 1)?, MikuType::U32(*limb))?;
+        }
+
+        Ok(ptr)
+    }
+
+    /// Reads a big integer stored like [`MikuVM::alloc_bigint`] does.
+    ///
+    /// # Returns
+    /// - `Ok(BigInt)` the value.
+    /// - [`MikuError::InvalidBigInt`] if the pointer doesn't point to a big integer.
+    /// - Any error of [`MikuVM::deref_ptr`] or [`MikuVM::offset_ptr`].
+    pub fn read_bigint(&mut self, ptr: MikuType) -> Result<BigInt, MikuError> {
+        let len = match self.deref_ptr(ptr)? {
+            MikuType::I64(len) => len,
+            _ => return Err(MikuError::InvalidBigInt(ptr)),
+        };
+
+        let limbs = (1..=len.unsigned_abs())
+            .map(|i| match self.deref_ptr(self.offset_ptr(ptr, i as i64)?)? {
+                MikuType::U32(limb) => Ok(limb),
+                _ => Err(MikuError::InvalidBigInt(ptr)),
+            })
+            .collect::<Result<Vec<_>, MikuError>>()?;
+        Ok(BigInt::from_limbs(len < 0, limbs))
+    }
+
+    /// The number of limbs of the big integer the pointer points to, read without firing
+    /// watchpoints. `None` if the pointer doesn't point to a big integer.
+    pub fn bigint_limbs(&self, ptr: MikuType) -> Option<u64> {
+        match self.memory[self.resolve_ptr(ptr).ok()?] {
+            MikuType::I64(len) => Some(len.unsigned_abs()),
+            _ => None,
+        }
+    }
+
+    /// Sets the number of live heap cells above which allocations trigger a garbage collection.
+    /// `None` disables automatic collection.
+    pub fn set_gc_threshold(&mut self, gc_threshold: Option<usize>) {
+        self.heap.set_gc_threshold(gc_threshold);
+    }
+
+    /// The statistics of the garbage collector.
+    pub fn gc_stats(&self) -> GcStats {
+        self.heap.stats()
+    }
+
+    /// The heap allocator.
+    pub fn heap(&self) -> &Heap {
+        &self.heap
+    }
+
+    /// Replaces the byte-addressed memory with a zeroed one of the given size in bytes.
+    /// Devices mapped into the old byte memory are removed.
+    pub fn set_byte_memory(&mut self, size: usize) {
+        self.byte_memory = ByteMemory::new(size);
+        self.byte_devices.clear();
+    }
+
+    /// The byte-addressed memory.
+    pub fn byte_memory(&self) -> &ByteMemory {
+        &self.byte_memory
+    }
+
+    /// Loads a value of the given type from the byte-addressed memory.
+    ///
+    /// # Returns
+    /// - `Ok(MikuType)` on successful load.
+    /// - [`MikuError::SegmentationFault`] if the pointer is [`MikuType::NULL`].
+    /// - [`MikuError::InvalidPointerType`] if the pointer isn't a [`MikuType::Ptr`] into
+    ///   [`Segment::Bytes`].
+    /// - [`MikuError::DeviceTypeMismatch`] if the device mapped onto the offset returns a value of
+    ///   another type.
+    /// - Any error of [`ByteMemory::load`] or of the [`Device`] mapped onto the offset.
+    pub fn load_bytes(&mut self, ptr: MikuType, type_id: u8) -> Result<MikuType, MikuError> {
+        self.faulting_at(Self::ptr_address(ptr), |vm| {
+            let offset = Self::byte_offset(ptr)?;
+            let range = vm.byte_memory.access_range(offset, type_id)?;
+            vm.debugger.on_byte_access(range, WatchKind::Read);
+
+            match vm.byte_device_at(offset) {
+                Some(device) => match device.read(offset)? {
+                    value if value.type_id() == type_id => Ok(value),
+                    value => Err(MikuError::DeviceTypeMismatch(type_id, value)),
+                },
+                None => vm.byte_memory.load(offset, type_id),
+            }
+        })
+    }
+
+    /// Stores a value in the byte-addressed memory.
+    ///
+    /// # Returns
+    /// - `Ok(())` on successful store.
+    /// - [`MikuError::SegmentationFault`] if the pointer is [`MikuType::NULL`].
+    /// - [`MikuError::InvalidPointerType`] if the pointer isn't a [`MikuType::Ptr`] into
+    ///   [`Segment::Bytes`].
+    /// - [`MikuError::ProtectionFault`] if the byte memory is read-only.
+    /// - Any error of [`ByteMemory::store`] or of the [`Device`] mapped onto the offset.
+    pub fn store_bytes(&mut self, ptr: MikuType, data: MikuType) -> Result<(), MikuError> {
+        self.faulting_at(Self::ptr_address(ptr), |vm| {
+            let offset = Self::byte_offset(ptr)?;
+            let range = vm.byte_memory.access_range(offset, data.type_id())?;
+            vm.check_writable(Segment::Bytes)?;
+            vm.debugger.on_byte_access(range, WatchKind::Write);
+
+            match vm.byte_device_at(offset) {
+                Some(device) => device.write(offset, data),
+                None => vm.byte_memory.store(offset, data),
+            }
+        })
+    }
+
+    /// Turns a pointer into the byte memory into an offset.
+    fn byte_offset(ptr: MikuType) -> Result<usize, MikuError> {
+        match ptr {
+            MikuType::Ptr(pointer) if pointer.segment() == Segment::Bytes => usize::try_from(pointer.address()).map_err(|_| MikuError::SegmentationFault),
+            MikuType::NULL => Err(MikuError::SegmentationFault),
+            _ => Err(MikuError::InvalidPointerType(ptr)),
+        }
+    }
+
+    /// Returns the device mapped onto the given byte memory offset.
+    fn byte_device_at(&mut self, offset: usize) -> Option<&mut MappedDevice> {
+        self.byte_devices.iter_mut().find(|mapped| mapped.contains(offset))
+    }
+
+    /// Declare a struct layout under the given id.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the layout was successfully declared.
+    /// - [`MikuError::StructRedefinition`] if a layout is already declared under the id.
+    pub fn define_struct(&mut self, id: usize, layout: StructLayout) -> Result<(), MikuError> {
+        if self.structs.contains_key(&id) {
+            return Err(MikuError::StructRedefinition(id));
+        }
+
+        self.structs.insert(id, layout);
+        Ok(())
+    }
+
+    /// Returns the struct layout declared under the given id.
+    pub fn struct_layout(&self, id: usize) -> Option<&StructLayout> {
+        self.structs.get(&id)
+    }
+
+    /// Read a field of the record the pointer points to.
+    ///
+    /// # Returns
+    /// - `Ok(MikuType)` if the field holds a value of the declared type.
+    /// - [`MikuError::UndefinedStruct`] or [`MikuError::UndefinedField`] if the layout or the
+    ///   field doesn't exist.
+    /// - [`MikuError::FieldTypeMismatch`] if the stored value isn't of the declared type.
+    /// - Any error of [`MikuVM::deref_ptr`].
+    pub fn get_field(&mut self, ptr: MikuType, struct_id: usize, field: usize) -> Result<MikuType, MikuError> {
+        let (field_ptr, type_id) = self.field_ptr(ptr, struct_id, field)?;
+        let value = self.deref_ptr(field_ptr)?;
+
+        if value.type_id() != type_id {
+            return Err(MikuError::FieldTypeMismatch(type_id, value));
+        }
+
+        Ok(value)
+    }
+
+    /// Write a field of the record the pointer points to.
+    ///
+    /// # Returns
+    /// - `Ok(())` on successful write.
+    /// - [`MikuError::UndefinedStruct`] or [`MikuError::UndefinedField`] if the layout or the
+    ///   field doesn't exist.
+    /// - [`MikuError::FieldTypeMismatch`] if the value isn't of the declared type.
+    /// - Any error of [`MikuVM::write_ptr`].
+    pub fn set_field(&mut self, ptr: MikuType, struct_id: usize, field: usize, data: MikuType) -> Result<(), MikuError> {
+        let (field_ptr, type_id) = self.field_ptr(ptr, struct_id, field)?;
+
+        if data.type_id() != type_id {
+            return Err(MikuError::FieldTypeMismatch(type_id, data));
+        }
+
+        self.write_ptr(field_ptr, data)
+    }
+
+    /// Resolves a pointer to a record into a pointer to one of its fields and the field's type.
+    fn field_ptr(&self, ptr: MikuType, struct_id: usize, field: usize) -> Result<(MikuType, u8), MikuError> {
+        let layout = self.structs.get(&struct_id).ok_or(MikuError::UndefinedStruct(struct_id))?;
+        let field = layout.field(field).ok_or(MikuError::UndefinedField(struct_id, field))?;
+
+        let offset = i64::try_from(field.offset()).map_err(|_| MikuError::SegmentationFault)?;
+        Ok((self.offset_ptr(ptr, offset)?, field.type_id()))
     }
     
     /// Push a [`MikuType`] onto the stack.
@@ -128,57 +902,406 @@ impl<'a> MikuVM<'a> {
     /// # Returns
     /// - `Ok(())` on successful push.
     /// - [`MikuError::StackOverflow`] if the stack is out of space.
+    /// - [`MikuError::ProtectionFault`] if the stack is read-only.
     pub fn stack_push(&mut self, stack_entry: MikuType) -> Result<(), MikuError> {
-        if self.stack_top == STACK_END {
-            return Err(MikuError::StackOverflow);           
+        if self.stack_top == self.layout.segment(Segment::Stack).end {
+            self.fault_address.get_or_insert(self.stack_top);
+            return Err(MikuError::StackOverflow);
         }
+        let top = self.stack_top;
+        self.faulting_at(Some(top), |vm| vm.check_writable(Segment::Stack))?;
+        self.debugger.on_access(self.stack_top..self.stack_top + 1, WatchKind::Write);
 
+        self.notify(|observer| observer.on_push(stack_entry));
         self.memory[self.stack_top] = stack_entry;
         self.stack_top += 1;
         Ok(())
     }
     
+    /// Returns the top entry of the stack without popping it or firing watchpoints.
+    pub fn stack_peek(&self) -> Option<MikuType> {
+        self.stack_peek_at(0)
+    }
+
+    /// Returns the entry the given number of entries below the top of the stack without popping
+    /// it or firing watchpoints.
+    pub fn stack_peek_at(&self, depth: usize) -> Option<MikuType> {
+        (self.stack_top - self.stack_base > depth).then(|| self.memory[self.stack_top - 1 - depth])
+    }
+
     /// Pops the top entry off the stack. 
     /// # Returns
-    /// - `Ok(())` on successful pop.
+    /// - `Ok(MikuType)` the popped entry on successful pop.
     /// - [`MikuError::StackUnderflow`] if the stack is empty. 
-    pub fn stack_pop(&mut self) -> Result<(), MikuError> {
+    pub fn stack_pop(&mut self) -> Result<MikuType, MikuError> {
         if self.stack_base == self.stack_top {
             return Err(MikuError::StackUnderflow);
         }
 
         self.stack_top -= 1;
-        Ok(())
+        self.debugger.on_access(self.stack_top..self.stack_top + 1, WatchKind::Read);
+        let value = self.memory[self.stack_top];
+        self.notify(|observer| observer.on_pop(value));
+        Ok(value)
     }
     
+    /// Converts the operands of an arithmetic operation to a common type according to the
+    /// promotion policy of the vm. See [`MikuType::promote`].
+    pub fn promote(&self, lhs: MikuType, rhs: MikuType) -> Result<(MikuType, MikuType), MikuError> {
+        lhs.promote(rhs, self.promotion)
+    }
+
+    /// Sets the policy used by the arithmetic instructions to reconcile mismatched operand types.
+    pub fn set_promotion(&mut self, promotion: Promotion) {
+        self.promotion = promotion;
+    }
+
+    /// The policy used by the arithmetic instructions to reconcile mismatched operand types.
+    pub fn promotion(&self) -> Promotion {
+        self.promotion
+    }
+
+    /// Adds a breakpoint on the instruction at the given program counter.
+    /// # Returns
+    /// - `true` if there was no breakpoint there yet.
+    pub fn add_breakpoint(&mut self, pc: usize) -> bool {
+        self.debugger.add_breakpoint(pc)
+    }
+
+    /// Removes the breakpoint at the given program counter.
+    /// # Returns
+    /// - `true` if there was a breakpoint there.
+    pub fn remove_breakpoint(&mut self, pc: usize) -> bool {
+        self.debugger.remove_breakpoint(pc)
+    }
+
+    /// Watches the given memory addresses for the given kind of access.
+    /// # Returns
+    /// - The id of the watchpoint, reported when it fires.
+    pub fn add_watchpoint(&mut self, range: Range<usize>, kind: WatchKind) -> usize {
+        self.debugger.add_watchpoint(Watchpoint::new(range, kind))
+    }
+
+    /// Watches the given offsets of the byte memory for the given kind of access.
+    /// # Returns
+    /// - The id of the watchpoint, reported when it fires.
+    pub fn add_byte_watchpoint(&mut self, range: Range<usize>, kind: WatchKind) -> usize {
+        self.debugger.add_watchpoint(Watchpoint::bytes(range, kind))
+    }
+
+    /// Removes the watchpoint with the given id.
+    /// # Returns
+    /// - `Some(Watchpoint)` the removed watchpoint.
+    /// - `None` if there is no watchpoint with the id.
+    pub fn remove_watchpoint(&mut self, id: usize) -> Option<Watchpoint> {
+        self.debugger.remove_watchpoint(id)
+    }
+
+    /// The breakpoints and watchpoints.
+    pub fn debugger(&self) -> &Debugger {
+        &self.debugger
+    }
+
+    /// The remaining gas. `None` if execution isn't metered.
+    pub fn gas(&self) -> Option<u64> {
+        self.gas
+    }
+
+    /// Sets the gas budget. `None` disables metering.
+    pub fn set_gas(&mut self, gas: Option<u64>) {
+        self.gas = gas;
+    }
+
+    /// Tops up the gas budget. Does nothing if execution isn't metered.
+    pub fn add_gas(&mut self, gas: u64) {
+        self.gas = self.gas.map(|remaining| remaining.saturating_add(gas));
+    }
+
+    /// Charges the given amount of gas. Custom instructions can use this for extra costs.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the gas was charged or execution isn't metered.
+    /// - [`MikuError::OutOfGas`] if there isn't enough gas left. Nothing is charged then.
+    pub fn charge_gas(&mut self, cost: u64) -> Result<(), MikuError> {
+        match self.gas {
+            Some(remaining) if remaining < cost => Err(MikuError::OutOfGas(cost)),
+            Some(remaining) => {
+                self.gas = Some(remaining - cost);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// The gas costs of the instructions.
+    pub fn gas_table(&self) -> &GasTable {
+        &self.gas_table
+    }
+
+    /// Replaces the gas costs of the instructions.
+    pub fn set_gas_table(&mut self, gas_table: GasTable) {
+        self.gas_table = gas_table;
+    }
+
     /// Increment the program counter by 1.
     pub fn inc_pc(&mut self) {
         self.pc += 1;
     }
     
     /// Pushes an instruciton into the program.
-    pub fn push_inst(&mut self, inst: &'a Box<dyn Inst>) {
+    pub fn push_inst(&mut self, inst: Box<dyn Inst>) {
         self.program.push(inst);
     }
 
+    /// Replaces the program and resets the program counter.
+    pub fn load_program(&mut self, program: Vec<Box<dyn Inst>>) {
+        self.set_program(Program::new(program));
+    }
+
+    /// Replaces the program with a shared one and resets the program counter.
+    /// Pending interrupts were meant for the old program and are withdrawn.
+    pub fn set_program(&mut self, program: Program) {
+        self.interrupt.clear();
+        self.program = program;
+        self.pc = 0;
+    }
+
+    /// The loaded program.
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Serializes the state of the vm and its program. See [`crate::snapshot`] for the format
+    /// and the state that isn't included.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut writer = SnapshotWriter::new();
+
+        writer.usize(self.layout.memory_size());
+        writer.usize(self.layout.segment(Segment::Stack).len());
+        writer.usize(self.layout.segment(Segment::Data).len());
+        writer.usize(self.byte_memory.len());
+        writer.option(self.heap.gc_threshold().map(|threshold| threshold as u64));
+        writer.option(self.gas);
+
+        self.memory.iter().for_each(|cell| writer.value(*cell));
+        for register in [self.stack_top, self.stack_base, self.largest_data_address, self.largest_heap_address, self.pc] {
+            writer.usize(register);
+        }
+
+        for segment in [Segment::Stack, Segment::Data, Segment::Heap, Segment::Bytes] {
+            writer.u8(match self.access(segment) {
+                Access::ReadWrite => 0,
+                Access::ReadOnly => 1,
+            });
+        }
+        writer.u8(self.initialised as u8);
+        writer.u8(match self.promotion {
+            Promotion::Strict => 0,
+            Promotion::Widening => 1,
+        });
+        writer.raw(self.byte_memory.bytes());
+
+        let mut structs: Vec<_> = self.structs.iter().collect();
+        structs.sort_by_key(|(id, _)| **id);
+        writer.usize(structs.len());
+        for (id, layout) in structs {
+            writer.usize(*id);
+            writer.raw(&Vec::from(layout));
+        }
+
+        writer.usize(self.heap.blocks().count());
+        for (start, size) in self.heap.blocks() {
+            writer.usize(start);
+            writer.usize(size);
+        }
+        let stats = self.heap.stats();
+        for stat in [stats.collections, stats.freed_blocks, stats.freed_cells, stats.live_blocks, stats.live_cells] {
+            writer.usize(stat);
+        }
+
+        writer.bytes(&self.program.encode());
+        writer.finish()
+    }
+
+    /// Creates a vm from a snapshot taken with [`MikuVM::snapshot`].
+    ///
+    /// # Returns
+    /// - `Ok(MikuVM)` the restored vm.
+    /// - [`MikuError::InvalidSnapshot`] if the snapshot is malformed.
+    /// - [`MikuError::UnsupportedSnapshotVersion`] if the snapshot was written by an incompatible
+    ///   version of the vm.
+    /// - Any error of decoding the program. Use [`MikuVM::restore_with_registry`] for programs
+    ///   with custom instructions.
+    pub fn restore(bytes: &[u8]) -> Result<Self, MikuError> {
+        Self::restore_with_registry(bytes, &InstRegistry::new())
+    }
+
+    /// Creates a vm from a snapshot decoding its program with the given registry.
+    /// See [`MikuVM::restore`].
+    pub fn restore_with_registry(bytes: &[u8], registry: &InstRegistry) -> Result<Self, MikuError> {
+        let mut reader = SnapshotReader::new(bytes)?;
+
+        let memory_size = reader.usize()?;
+        let mut config = VmConfigBuilder::new()
+            .memory_size(memory_size)
+            .stack_size(reader.usize()?)
+            .data_size(reader.usize()?);
+        let byte_memory_size = reader.usize()?;
+        // Every cell takes at least one byte, so sizes larger than the rest of the snapshot are
+        // rejected before anything is allocated.
+        if memory_size > reader.remaining().len() || byte_memory_size > reader.remaining().len() {
+            return Err(MikuError::InvalidSnapshot("SIZES EXCEED THE SNAPSHOT".to_string()));
+        }
+        if let Some(threshold) = reader.option()? {
+            config = config.gc_threshold(threshold as usize);
+        }
+        if let Some(gas) = reader.option()? {
+            config = config.gas_limit(gas);
+        }
+        let mut vm = Self::with_config(config.build()?);
+
+        for cell in vm.memory.iter_mut() {
+            *cell = reader.value()?;
+        }
+        vm.stack_top = reader.usize()?;
+        vm.stack_base = reader.usize()?;
+        vm.largest_data_address = reader.usize()?;
+        vm.largest_heap_address = reader.usize()?;
+        vm.pc = reader.usize()?;
+
+        for segment in [Segment::Stack, Segment::Data, Segment::Heap, Segment::Bytes] {
+            let access = match reader.u8()? {
+                0 => Access::ReadWrite,
+                1 => Access::ReadOnly,
+                access => return Err(MikuError::InvalidSnapshot(format!("INVALID ACCESS {}", access))),
+            };
+            vm.set_access(segment, access);
+        }
+        vm.initialised = reader.u8()? != 0;
+        vm.promotion = match reader.u8()? {
+            0 => Promotion::Strict,
+            1 => Promotion::Widening,
+            promotion => return Err(MikuError::InvalidSnapshot(format!("INVALID PROMOTION {}", promotion))),
+        };
+        vm.byte_memory = ByteMemory::from(reader.raw(byte_memory_size)?.to_vec());
+
+        for _ in 0..reader.usize()? {
+            let id = reader.usize()?;
+            let layout_bytes = reader.raw(StructLayout::get_bytes_length(reader.remaining())?)?;
+            vm.structs.insert(id, StructLayout::try_from(layout_bytes)?);
+        }
+
+        let blocks = (0..reader.usize()?)
+            .map(|_| Ok((reader.usize()?, reader.usize()?)))
+            .collect::<Result<Vec<_>, MikuError>>()?;
+        let stats = GcStats {
+            collections: reader.usize()?,
+            freed_blocks: reader.usize()?,
+            freed_cells: reader.usize()?,
+            live_blocks: reader.usize()?,
+            live_cells: reader.usize()?,
+        };
+        vm.heap.restore_blocks(blocks, stats)?;
+
+        vm.program = Program::new(registry.decode_program(reader.bytes()?)?);
+        reader.finish()?;
+
+        let stack = vm.layout.segment(Segment::Stack);
+        if vm.stack_base > vm.stack_top || vm.stack_base < stack.start || vm.stack_top > stack.end || vm.pc > vm.program.len() {
+            return Err(MikuError::InvalidSnapshot("REGISTERS OUT OF BOUNDS".to_string()));
+        }
+
+        Ok(vm)
+    }
+
+    /// Describes the state of the vm as JSON for external tools. See [`crate::json`].
+    ///
+    /// # Members
+    /// - `registers`: `pc`, `stack_top`, `stack_base` and `gas` (`null` without a budget).
+    /// - `layout`: the memory size and the `[start, end)` range of every segment.
+    /// - `stack_frames`: the `pc`, `base`, `top` and `entries` of every stack frame.
+    /// - `data`: the `address` and `value` of every .data cell that isn't [`MikuType::NULL`].
+    /// - `heap_blocks`: the `start`, `size` and `cells` of every allocated heap block.
+    /// - `program`: the `pc`, `opcode` and disassembled `inst` of every instruction.
+    pub fn to_json(&self) -> Json {
+        let segment = |segment: Segment| {
+            let range = self.layout.segment(segment);
+            Json::from_iter([range.start, range.end])
+        };
+        let cells = |range: Range<usize>| self.memory[range].iter().map(|cell| cell.to_string()).collect::<Json>();
+
+        let data = self.layout.segment(Segment::Data);
+        Json::object([
+            ("registers", Json::object([
+                ("pc", self.pc.into()),
+                ("stack_top", self.stack_top.into()),
+                ("stack_base", self.stack_base.into()),
+                ("gas", self.gas.into()),
+            ])),
+            ("layout", Json::object([
+                ("memory_size", self.layout.memory_size().into()),
+                ("stack", segment(Segment::Stack)),
+                ("data", segment(Segment::Data)),
+                ("heap", segment(Segment::Heap)),
+            ])),
+            ("stack_frames", self.frames().into_iter()
+                .map(|frame| Json::object([
+                    ("pc", frame.pc.into()),
+                    ("base", frame.base.into()),
+                    ("top", frame.top.into()),
+                    ("entries", cells(frame.base..frame.top)),
+                ]))
+                .collect()),
+            ("data", self.memory[data.clone()].iter().zip(data)
+                .filter(|(cell, _)| **cell != MikuType::NULL)
+                .map(|(cell, address)| Json::object([("address", address.into()), ("value", cell.to_string().into())]))
+                .collect()),
+            ("heap_blocks", self.heap.blocks()
+                .map(|(start, size)| Json::object([
+                    ("start", start.into()),
+                    ("size", size.into()),
+                    ("cells", cells(start..start + size)),
+                ]))
+                .collect()),
+            ("program", self.program.iter().enumerate()
+                .map(|(pc, inst)| Json::object([
+                    ("pc", pc.into()),
+                    ("opcode", (inst.opcode() as usize).into()),
+                    ("inst", inst.disassemble().into()),
+                ]))
+                .collect()),
+        ])
+    }
+
+    /// The frames of the stack, innermost first. Frames record the program counter of the
+    /// instruction they are executing.
+    pub fn frames(&self) -> Vec<StackFrame> {
+        self.backtrace(self.pc)
+    }
+
+    /// The frames of the stack with the innermost frame executing the given instruction.
+    fn backtrace(&self, pc: usize) -> Vec<StackFrame> {
+        vec![StackFrame { pc, base: self.stack_base, top: self.stack_top }]
+    }
+
     /// The stack memory.
     /// Returns a clone of the stack segment of the memory as a [`Vec`] of [`MikuType`].
     pub fn stack(&self) -> Vec<MikuType> {
-        self.memory[STACK_START..STACK_END].to_vec()
+        self.memory[self.layout.segment(Segment::Stack)].to_vec()
     }
     
     /// The data memory.
     /// The data memory holds the constants of the program.
     /// Returns a clone of the data segment of the memory as a [`Vec`] of [`MikuType`].
     pub fn data_mem(&self) -> Vec<MikuType> {
-        self.memory[DATA_START..DATA_END].to_vec()
+        self.memory[self.layout.segment(Segment::Data)].to_vec()
     }
     
     /// The heap memory.
     /// The heap memroy holds the dynamically allocated data of the program.
     /// Returns a clone of the heap segment of the memory as a [`Vec`] of [`MikuType`].
     pub fn heap_mem(&self) -> Vec<MikuType> {
-        self.memory[HEAP_START..HEAP_END].to_vec()
+        self.memory[self.layout.segment(Segment::Heap)].to_vec()
     }
 
     /// The program counter.
@@ -198,17 +1321,42 @@ impl<'a> MikuVM<'a> {
     }
 }
 
-impl Display for MikuVM<'_> {
+impl Default for MikuVM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Formats the program with a `>` in front of the next instruction and the used cells of
+/// the stack, .data and heap segments, one cell per line.
+impl Display for MikuVM {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, 
-            "----------- VM -----------\n  
-            Program: {:?}\n  
-            Stack: {:?}\n  
-            Data: {:?}\n  
-            Heap: {:?}", 
-            self.program, 
-            &self.memory[STACK_START..self.stack_top], 
-            &self.memory[DATA_START..self.largest_data_address + 1], 
-            &self.memory[HEAP_START..self.largest_heap_address])
+        writeln!(f, "----------- VM -----------")?;
+        writeln!(f, "pc: {}  stack top: {}  stack base: {}", self.pc, self.stack_top, self.stack_base)?;
+
+        writeln!(f, "Program:")?;
+        for (pc, inst) in self.program.iter().enumerate() {
+            let marker = if pc == self.pc { ">" } else { " " };
+            writeln!(f, "{} {:>4}: {}", marker, pc, inst.disassemble())?;
+        }
+
+        writeln!(f, "Stack:")?;
+        for address in self.stack_base..self.stack_top {
+            writeln!(f, "  {:>4}: {}", address, self.memory[address])?;
+        }
+
+        writeln!(f, "Data:")?;
+        for address in self.layout.segment(Segment::Data).filter(|address| self.memory[*address] != MikuType::NULL) {
+            writeln!(f, "  {:>4}: {}", address, self.memory[address])?;
+        }
+
+        write!(f, "Heap:")?;
+        for (start, size) in self.heap.blocks() {
+            write!(f, "\n  block {}..{}", start, start + size)?;
+            for address in start..start + size {
+                write!(f, "\n  {:>4}: {}", address, self.memory[address])?;
+            }
+        }
+        Ok(())
     }
 }
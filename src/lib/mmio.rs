@@ -0,0 +1,128 @@
+//! Memory-mapped I/O.
+//!
+//! Host devices can be mapped onto a range of memory addresses of [`crate::miku::MikuVM`].
+//! Reads and writes through pointers into that range are routed to the [`Device`] instead of
+//! the memory, so bytecode can talk to the host with ordinary memory accesses.
+//!
+//! ## Examples
+//! ``` rust
+//! use vm::{miku::MikuVM, mmio::Callbacks, types::{MikuType, Pointer, Segment}, HEAP_START};
+//!
+//! let mut vm = MikuVM::new();
+//! let mut ticks = 0;
+//! let counter = Callbacks::new(
+//!     move |_| { ticks += 1; Ok(MikuType::U64(ticks)) },
+//!     |_, _| Ok(()),
+//! );
+//! vm.map_device(HEAP_START..HEAP_START + 1, Box::new(counter)).unwrap();
+//!
+//! let ptr = MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_START as u64));
+//! assert_eq!(MikuType::U64(1), vm.deref_ptr(ptr).unwrap());
+//! assert_eq!(MikuType::U64(2), vm.deref_ptr(ptr).unwrap());
+//! ```
+
+use std::{fmt::Debug, ops::Range};
+
+use crate::{error::MikuError, types::MikuType};
+
+/// # The device trait.
+///
+/// This trait needs to be implemented by anything that wants to be mapped into the memory of
+/// [`crate::miku::MikuVM`]. The offsets are relative to the start of the mapped range.
+/// A mapped device is owned by the vm, which can be moved to another thread, so it has to be
+/// [`Send`].
+pub trait Device: Debug + Send {
+    /// Called when the vm reads a cell of the mapped range.
+    /// # Returns
+    /// - `Ok(MikuType)` the value of the cell.
+    /// - [`MikuError`] if the device can't be read.
+    fn read(&mut self, offset: usize) -> Result<MikuType, MikuError>;
+
+    /// Called when the vm writes a cell of the mapped range.
+    /// # Returns
+    /// - `Ok(())` if the write was successful.
+    /// - [`MikuError`] if the device can't be written.
+    fn write(&mut self, offset: usize, value: MikuType) -> Result<(), MikuError>;
+}
+
+/// A [`Device`] made of a read and a write callback.
+pub struct Callbacks<R, W>
+where
+    R: FnMut(usize) -> Result<MikuType, MikuError>,
+    W: FnMut(usize, MikuType) -> Result<(), MikuError>,
+{
+    read: R,
+    write: W,
+}
+
+impl<R, W> Callbacks<R, W>
+where
+    R: FnMut(usize) -> Result<MikuType, MikuError>,
+    W: FnMut(usize, MikuType) -> Result<(), MikuError>,
+{
+    pub fn new(read: R, write: W) -> Self {
+        Self { read, write }
+    }
+}
+
+impl<R, W> Debug for Callbacks<R, W>
+where
+    R: FnMut(usize) -> Result<MikuType, MikuError>,
+    W: FnMut(usize, MikuType) -> Result<(), MikuError>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Callbacks").finish_non_exhaustive()
+    }
+}
+
+impl<R, W> Device for Callbacks<R, W>
+where
+    R: FnMut(usize) -> Result<MikuType, MikuError> + Send,
+    W: FnMut(usize, MikuType) -> Result<(), MikuError> + Send,
+{
+    fn read(&mut self, offset: usize) -> Result<MikuType, MikuError> {
+        (self.read)(offset)
+    }
+
+    fn write(&mut self, offset: usize, value: MikuType) -> Result<(), MikuError> {
+        (self.write)(offset, value)
+    }
+}
+
+/// A device mapped onto a range of memory addresses.
+#[derive(Debug)]
+pub struct MappedDevice {
+    range: Range<usize>,
+    device: Box<dyn Device>,
+}
+
+impl MappedDevice {
+    pub fn new(range: Range<usize>, device: Box<dyn Device>) -> Self {
+        Self { range, device }
+    }
+
+    /// The mapped addresses.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Returns true if the given address is mapped to this device.
+    pub fn contains(&self, address: usize) -> bool {
+        self.range.contains(&address)
+    }
+
+    /// Reads the cell at the given absolute address.
+    pub fn read(&mut self, address: usize) -> Result<MikuType, MikuError> {
+        self.device.read(address - self.range.start)
+    }
+
+    /// Writes the cell at the given absolute address.
+    pub fn write(&mut self, address: usize, value: MikuType) -> Result<(), MikuError> {
+        self.device.write(address - self.range.start, value)
+    }
+
+    /// Unwraps the device.
+    pub fn into_device(self) -> Box<dyn Device> {
+        self.device
+    }
+}
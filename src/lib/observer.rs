@@ -0,0 +1,60 @@
+//! Execution observers.
+//!
+//! Observers registered on a [`crate::miku::MikuVM`] are notified about every executed
+//! instruction, memory write, stack operation and error. They can be used to build tracers,
+//! profilers and coverage tools without changing the vm. A vm without observers doesn't pay for
+//! the notifications.
+//!
+//! ## Examples
+//! ``` rust
+//! use std::sync::{Arc, Mutex};
+//! use vm::{inst::*, miku::MikuVM, observer::Observer, types::MikuType};
+//!
+//! #[derive(Debug)]
+//! struct Coverage(Arc<Mutex<Vec<usize>>>);
+//!
+//! impl Observer for Coverage {
+//!     fn before_inst(&mut self, pc: usize, _: &dyn Inst) {
+//!         self.0.lock().unwrap().push(pc);
+//!     }
+//! }
+//!
+//! let covered = Arc::new(Mutex::new(Vec::new()));
+//! let mut vm = MikuVM::from_program(vec![
+//!     Box::new(Push::new(MikuType::U8(1))),
+//!     Box::new(Pop::new()),
+//! ]);
+//! vm.add_observer(Box::new(Coverage(covered.clone())));
+//! vm.run_program().unwrap();
+//! assert_eq!(vec![0, 1], *covered.lock().unwrap());
+//! ```
+
+use std::fmt::Debug;
+
+use crate::{error::MikuError, inst::Inst, types::MikuType};
+
+/// # The observer trait.
+///
+/// Every callback does nothing by default so observers only implement the ones they need.
+/// Observers are [`Send`] so a traced vm can still be handed to a worker thread.
+pub trait Observer: Debug + Send {
+    /// Called before the instruction at the given program counter is executed.
+    fn before_inst(&mut self, _pc: usize, _inst: &dyn Inst) {}
+
+    /// Called after the instruction at the given program counter was executed successfully.
+    fn after_inst(&mut self, _pc: usize, _inst: &dyn Inst) {}
+
+    /// Called when a memory cell is overwritten, including the cells reset by
+    /// [`crate::miku::MikuVM::free`] and the garbage collector. Writes routed to devices and
+    /// stack pushes (see [`Observer::on_push`]) aren't reported.
+    fn on_memory_write(&mut self, _address: usize, _old: MikuType, _new: MikuType) {}
+
+    /// Called when a value is pushed onto the stack.
+    fn on_push(&mut self, _value: MikuType) {}
+
+    /// Called when a value is popped off the stack.
+    fn on_pop(&mut self, _value: MikuType) {}
+
+    /// Called when the instruction at the given program counter fails.
+    fn on_error(&mut self, _pc: usize, _error: &MikuError) {}
+}
@@ -0,0 +1,409 @@
+//! Bytecode programs.
+//!
+//! A compiled program is the encodings of its instructions (see [`Inst::encode`]) written one
+//! after the other. The first byte of each instruction is its opcode, which selects the rules
+//! used to find where the instruction ends and how it is decoded.
+//!
+//! Custom instructions live in the extended opcode namespace. Their encoding starts with
+//! [`EXTENDED_OPCODE`], followed by the extended opcode ([`prim@u16`]), the payload length
+//! ([`prim@u32`]) and the payload, see [`encode_extended`]. Embedders register a decoder for each
+//! extended opcode they claim in an [`InstRegistry`].
+//!
+//! ## Examples
+//! ``` rust
+//! use vm::{inst::*, program::{decode_program, encode_program}, types::MikuType};
+//!
+//! let program: Vec<Box<dyn Inst>> = vec![
+//!     Box::new(Push::new(MikuType::U8(69))),
+//!     Box::new(Pop::new()),
+//! ];
+//! let bytes = encode_program(&program);
+//! assert_eq!(vec![0x00, 0x00, 0x45, 0x01], bytes);
+//! assert_eq!(2, decode_program(&bytes).unwrap().len());
+//! ```
+//!
+//! A [`Program`] is decoded once and shared by any number of vms, also across threads.
+//! ``` rust
+//! use std::thread;
+//! use vm::{miku::MikuVM, program::Program};
+//!
+//! let program = Program::decode(&[0x00, 0x00, 0x45, 0x01]).unwrap();
+//! thread::scope(|scope| {
+//!     for _ in 0..4 {
+//!         let program = program.clone();
+//!         scope.spawn(move || {
+//!             let mut vm = MikuVM::new();
+//!             vm.set_program(program);
+//!             vm.run_program().unwrap();
+//!         });
+//!     }
+//! });
+//! ```
+
+use std::{collections::HashMap, ops::Deref, sync::Arc};
+
+use crate::{error::MikuError, inst::*, layout::StructLayout, miku::MikuVM, tools, types::MikuType};
+
+/// The opcode that starts the encoding of every custom instruction.
+pub const EXTENDED_OPCODE: u8 = 0xFF;
+
+/// The length of the header of an extended instruction: opcode, extended opcode and payload length.
+const EXTENDED_HEADER_LENGTH: usize = 1 + size_of::<u16>() + size_of::<u32>();
+
+/// Calculates the length of the encoded instruction at the start of the given bytes.
+type LengthRule = fn(&[u8]) -> Result<usize, MikuError>;
+/// Decodes an instruction from exactly its encoded bytes.
+pub type Decoder = fn(&[u8]) -> Result<Box<dyn Inst>, MikuError>;
+
+/// The decoding rules of each instruction indexed by opcode.
+const DISPATCH_TABLE: [(LengthRule, Decoder); 22] = [
+    (operand_length, decode_boxed::<Push>),
+    (opcode_length, decode_boxed::<Pop>),
+    (def_length, decode_boxed::<Def>),
+    (def_struct_length, decode_boxed::<DefStruct>),
+    (usize_pair_length, decode_boxed::<FieldGet>),
+    (usize_pair_length, decode_boxed::<FieldSet>),
+    (opcode_length, decode_boxed::<Plus>),
+    (opcode_length, decode_boxed::<Minus>),
+    (opcode_length, decode_boxed::<Mult>),
+    (opcode_length, decode_boxed::<Div>),
+    (type_id_length, decode_boxed::<Load>),
+    (opcode_length, decode_boxed::<Store>),
+    (opcode_length, decode_boxed::<Alloc>),
+    (opcode_length, decode_boxed::<Free>),
+    (opcode_length, decode_boxed::<ToBig>),
+    (opcode_length, decode_boxed::<BigPlus>),
+    (opcode_length, decode_boxed::<BigMinus>),
+    (opcode_length, decode_boxed::<BigMult>),
+    (opcode_length, decode_boxed::<BigDiv>),
+    (opcode_length, decode_boxed::<BigRem>),
+    (opcode_length, decode_boxed::<BigCmp>),
+    (type_id_length, decode_boxed::<FromBig>),
+];
+
+/// The decoders of the custom instructions indexed by their extended opcode.
+///
+/// ## Examples
+/// ``` rust
+/// # use vm::{error::MikuError, inst::Inst, miku::MikuVM, program::*};
+/// #[derive(Debug)]
+/// struct Nop;
+///
+/// impl Inst for Nop {
+///     fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+///         vm.inc_pc();
+///         Ok(())
+///     }
+///
+///     fn encode(&self) -> Vec<u8> {
+///         encode_extended(7, &[])
+///     }
+///
+///     fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+///         extended_payload(bytes)?;
+///         Ok(Nop)
+///     }
+/// }
+///
+/// let mut registry = InstRegistry::new();
+/// registry.register::<Nop>(7).unwrap();
+/// let program = registry.decode_program(&Nop.encode()).unwrap();
+/// assert_eq!(Nop.encode(), encode_program(&program));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InstRegistry {
+    extended: HashMap<u16, Decoder>,
+}
+
+impl InstRegistry {
+    /// Creates a registry that only knows the built-in instructions.
+    pub fn new() -> Self {
+        Self { extended: HashMap::new() }
+    }
+
+    /// Claims an extended opcode for the given instruction type.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the opcode was claimed.
+    /// - [`MikuError::OpcodeAlreadyRegistered`] if the opcode is already claimed.
+    pub fn register<I: Inst + 'static>(&mut self, opcode: u16) -> Result<(), MikuError> {
+        self.register_decoder(opcode, decode_boxed::<I>)
+    }
+
+    /// Claims an extended opcode with a decoder function.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the opcode was claimed.
+    /// - [`MikuError::OpcodeAlreadyRegistered`] if the opcode is already claimed.
+    pub fn register_decoder(&mut self, opcode: u16, decoder: Decoder) -> Result<(), MikuError> {
+        if self.extended.contains_key(&opcode) {
+            return Err(MikuError::OpcodeAlreadyRegistered(opcode));
+        }
+
+        self.extended.insert(opcode, decoder);
+        Ok(())
+    }
+
+    /// Returns true if the extended opcode is claimed.
+    pub fn is_registered(&self, opcode: u16) -> bool {
+        self.extended.contains_key(&opcode)
+    }
+
+    /// Decodes a whole program that can contain the registered custom instructions.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<Box<dyn Inst>>)` the instructions of the program.
+    /// - [`MikuError::UnknownOpcode`] if an instruction starts with an unknown opcode.
+    /// - [`MikuError::UnknownExtendedOpcode`] if a custom instruction isn't registered.
+    /// - [`MikuError::BytesConversionError`] if the bytes end in the middle of an instruction.
+    /// - Any error of the instructions' [`Inst::decode`].
+    pub fn decode_program(&self, bytes: &[u8]) -> Result<Vec<Box<dyn Inst>>, MikuError> {
+        self.decode(bytes, false)
+    }
+
+    /// Decodes a whole program like [`InstRegistry::decode_program`] but defers unknown opcodes
+    /// to run time. They are decoded as [`UnknownInst`]s, which fail when they are executed so a
+    /// trap handler for [`crate::trap::TrapKind::UnknownOpcode`] can deal with them. An unknown
+    /// custom instruction covers just its own encoding, an unknown built-in opcode covers the
+    /// rest of the program because its length can't be known.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<Box<dyn Inst>>)` the instructions of the program.
+    /// - [`MikuError::BytesConversionError`] if the bytes end in the middle of an instruction.
+    /// - Any error of the instructions' [`Inst::decode`].
+    pub fn decode_program_lenient(&self, bytes: &[u8]) -> Result<Vec<Box<dyn Inst>>, MikuError> {
+        self.decode(bytes, true)
+    }
+
+    fn decode(&self, bytes: &[u8], lenient: bool) -> Result<Vec<Box<dyn Inst>>, MikuError> {
+        let mut program = Vec::new();
+        let mut start = 0;
+
+        while start < bytes.len() {
+            let opcode = bytes[start];
+            let rules = match opcode {
+                EXTENDED_OPCODE => self.extended_decoder(&bytes[start..]).map(|decoder| (extended_length as LengthRule, decoder)),
+                _ => DISPATCH_TABLE.get(opcode as usize).copied().ok_or(MikuError::UnknownOpcode(opcode)),
+            };
+            let (length_rule, decoder) = match rules {
+                Ok(rules) => rules,
+                Err(MikuError::UnknownExtendedOpcode(_)) if lenient => (extended_length as LengthRule, decode_boxed::<UnknownInst> as Decoder),
+                Err(MikuError::UnknownOpcode(_)) if lenient => (rest_length as LengthRule, decode_boxed::<UnknownInst> as Decoder),
+                Err(error) => return Err(error),
+            };
+
+            let end = start + length_rule(&bytes[start..])?;
+            program.push(decoder(&bytes[start..end])?);
+            start = end;
+        }
+
+        Ok(program)
+    }
+
+    /// Looks up the decoder of the custom instruction at the start of the given bytes.
+    fn extended_decoder(&self, bytes: &[u8]) -> Result<Decoder, MikuError> {
+        let opcode = u16::from_le_bytes(tools::convert_bytes(bytes.get(1..3).ok_or(MikuError::BytesConversionError)?)?);
+        self.extended.get(&opcode).copied().ok_or(MikuError::UnknownExtendedOpcode(opcode))
+    }
+}
+
+/// An immutable program that can be shared between vms and threads.
+/// Clones share the instructions, so cloning a program is cheap.
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    insts: Arc<Vec<Arc<dyn Inst>>>,
+}
+
+impl Program {
+    pub fn new(program: Vec<Box<dyn Inst>>) -> Self {
+        Self { insts: Arc::new(program.into_iter().map(Arc::from).collect()) }
+    }
+
+    /// Decodes a program made of built-in instructions. See [`decode_program`].
+    pub fn decode(bytes: &[u8]) -> Result<Self, MikuError> {
+        decode_program(bytes).map(Self::new)
+    }
+
+    /// Encodes the program. See [`encode_program`].
+    pub fn encode(&self) -> Vec<u8> {
+        encode_program(&self.insts)
+    }
+
+    /// Appends an instruction. The instructions are copied first if the program is shared.
+    pub(crate) fn push(&mut self, inst: Box<dyn Inst>) {
+        Arc::make_mut(&mut self.insts).push(Arc::from(inst));
+    }
+}
+
+impl From<Vec<Box<dyn Inst>>> for Program {
+    fn from(value: Vec<Box<dyn Inst>>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl Deref for Program {
+    type Target = [Arc<dyn Inst>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.insts
+    }
+}
+
+/// Decodes a whole program made of built-in instructions.
+///
+/// # Returns
+/// - `Ok(Vec<Box<dyn Inst>>)` the instructions of the program.
+/// - [`MikuError::UnknownOpcode`] if an instruction starts with an unknown opcode.
+/// - [`MikuError::BytesConversionError`] if the bytes end in the middle of an instruction.
+/// - Any error of the instructions' [`Inst::decode`].
+pub fn decode_program(bytes: &[u8]) -> Result<Vec<Box<dyn Inst>>, MikuError> {
+    InstRegistry::new().decode_program(bytes)
+}
+
+/// Decodes a whole program made of built-in instructions and defers unknown opcodes to run time.
+/// See [`InstRegistry::decode_program_lenient`].
+pub fn decode_program_lenient(bytes: &[u8]) -> Result<Vec<Box<dyn Inst>>, MikuError> {
+    InstRegistry::new().decode_program_lenient(bytes)
+}
+
+/// Encodes a whole program. The result can be decoded with [`decode_program`].
+pub fn encode_program<I: AsRef<dyn Inst>>(program: &[I]) -> Vec<u8> {
+    program.iter().flat_map(|inst| inst.as_ref().encode()).collect()
+}
+
+/// Encodes a custom instruction with the given extended opcode and operand bytes.
+///
+/// # Panics
+/// - If the payload is longer than [`u32::MAX`] bytes.
+pub fn encode_extended(opcode: u16, payload: &[u8]) -> Vec<u8> {
+    let length = u32::try_from(payload.len()).expect("extended instruction payload is too long");
+    let mut encoded_instruction = vec![EXTENDED_OPCODE];
+    encoded_instruction.extend(opcode.to_le_bytes());
+    encoded_instruction.extend(length.to_le_bytes());
+    encoded_instruction.extend(payload);
+    encoded_instruction
+}
+
+/// Returns the operand bytes of an encoded custom instruction.
+///
+/// # Returns
+/// - `Ok(&[u8])` the payload.
+/// - [`MikuError::BytesConversionError`] if the bytes aren't exactly one custom instruction.
+pub fn extended_payload(bytes: &[u8]) -> Result<&[u8], MikuError> {
+    if bytes.first() != Some(&EXTENDED_OPCODE) || extended_length(bytes)? != bytes.len() {
+        return Err(MikuError::BytesConversionError);
+    }
+
+    Ok(&bytes[EXTENDED_HEADER_LENGTH..])
+}
+
+/// An instruction with an opcode that wasn't known when the program was decoded, see
+/// [`InstRegistry::decode_program_lenient`]. It keeps its bytes so the program can be encoded
+/// again.
+///
+/// Executing it fails with [`MikuError::UnknownOpcode`] or [`MikuError::UnknownExtendedOpcode`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownInst {
+    bytes: Vec<u8>,
+}
+
+impl UnknownInst {
+    /// The undecoded bytes.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The error executing the instruction fails with.
+    pub fn error(&self) -> MikuError {
+        match self.bytes.get(1..3) {
+            Some(&[low, high]) if self.bytes[0] == EXTENDED_OPCODE => MikuError::UnknownExtendedOpcode(u16::from_le_bytes([low, high])),
+            _ => MikuError::UnknownOpcode(self.bytes[0]),
+        }
+    }
+}
+
+impl Inst for UnknownInst {
+    fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+        vm.inc_pc();
+        Err(self.error())
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+        if bytes.is_empty() {
+            return Err(MikuError::BytesConversionError);
+        }
+        Ok(Self { bytes: bytes.to_vec() })
+    }
+
+    fn opcode(&self) -> u8 {
+        self.bytes[0]
+    }
+
+    fn disassemble(&self) -> String {
+        match self.error() {
+            MikuError::UnknownExtendedOpcode(opcode) => format!("unknown extended {}", opcode),
+            _ => format!("unknown {}", self.bytes[0]),
+        }
+    }
+}
+
+fn decode_boxed<I: Inst + 'static>(bytes: &[u8]) -> Result<Box<dyn Inst>, MikuError> {
+    Ok(Box::new(I::decode(bytes)?))
+}
+
+/// Checks that the bytes are at least as long as the instruction.
+fn checked_length(bytes: &[u8], length: usize) -> Result<usize, MikuError> {
+    match length <= bytes.len() {
+        true => Ok(length),
+        false => Err(MikuError::BytesConversionError),
+    }
+}
+
+/// Unknown instructions that take up the rest of the program.
+fn rest_length(bytes: &[u8]) -> Result<usize, MikuError> {
+    Ok(bytes.len())
+}
+
+/// Instructions without operands.
+fn opcode_length(_: &[u8]) -> Result<usize, MikuError> {
+    Ok(1)
+}
+
+/// Instructions with a type identifier operand.
+fn type_id_length(bytes: &[u8]) -> Result<usize, MikuError> {
+    checked_length(bytes, 2)
+}
+
+/// Instructions with a [`MikuType`] operand.
+fn operand_length(bytes: &[u8]) -> Result<usize, MikuError> {
+    let type_id = *bytes.get(1).ok_or(MikuError::BytesConversionError)?;
+    checked_length(bytes, 1 + MikuType::get_bytes_length(type_id)?)
+}
+
+/// Instructions with a [`MikuType`] and an address operand.
+fn def_length(bytes: &[u8]) -> Result<usize, MikuError> {
+    checked_length(bytes, operand_length(bytes)? + size_of::<usize>())
+}
+
+/// Instructions with an id and a [`StructLayout`] operand.
+fn def_struct_length(bytes: &[u8]) -> Result<usize, MikuError> {
+    let layout_start = checked_length(bytes, 1 + size_of::<usize>())?;
+    Ok(layout_start + StructLayout::get_bytes_length(&bytes[layout_start..])?)
+}
+
+/// Custom instructions.
+fn extended_length(bytes: &[u8]) -> Result<usize, MikuError> {
+    let header = bytes.get(3..EXTENDED_HEADER_LENGTH).ok_or(MikuError::BytesConversionError)?;
+    let payload_length = usize::try_from(u32::from_le_bytes(tools::convert_bytes(header)?))
+        .map_err(|_| MikuError::BytesConversionError)?;
+    checked_length(bytes, EXTENDED_HEADER_LENGTH.saturating_add(payload_length))
+}
+
+/// Instructions with two [`prim@usize`] operands.
+fn usize_pair_length(bytes: &[u8]) -> Result<usize, MikuError> {
+    checked_length(bytes, 1 + 2 * size_of::<usize>())
+}
@@ -0,0 +1,183 @@
+//! Binary snapshots of the vm state.
+//!
+//! [`crate::miku::MikuVM::snapshot`] serializes everything a program can observe so the run can
+//! be resumed with [`crate::miku::MikuVM::restore`], possibly in another process. Host-side
+//! state (mapped devices, observers, trap handlers, breakpoints, watchpoints and the gas cost
+//! table) isn't part of a snapshot and has to be set up again after restoring.
+//!
+//! # Format (version 1)
+//! All integers are little endian, sizes and addresses are stored as [`prim@u64`].
+//! - magic `MIKU` followed by the format version ([`prim@u16`]).
+//! - memory, stack and .data sizes, byte memory size.
+//! - gc threshold and gas as a presence byte followed by the value.
+//! - memory cells encoded as [`crate::types::MikuType`]s.
+//! - stack top, stack base, largest data address, largest heap address and program counter.
+//! - segment permissions (1 byte each for stack, .data, heap and byte memory), initialised flag,
+//!   promotion.
+//! - the byte memory.
+//! - struct layouts as count followed by id and encoded [`crate::layout::StructLayout`].
+//! - heap blocks as count followed by start and size, then the 5 [`crate::heap::GcStats`].
+//! - program as byte length followed by the encoded program.
+//!
+//! ## Examples
+//! ``` rust
+//! use vm::{inst::*, miku::MikuVM, types::MikuType};
+//!
+//! let mut vm = MikuVM::from_program(vec![
+//!     Box::new(Push::new(MikuType::U8(1))),
+//!     Box::new(Push::new(MikuType::U8(2))),
+//! ]);
+//! vm.step();
+//! let mut restored = MikuVM::restore(&vm.snapshot()).unwrap();
+//! restored.run_program().unwrap();
+//! assert_eq!(vec![MikuType::U8(1), MikuType::U8(2)], restored.stack()[0..2].to_vec());
+//! ```
+
+use crate::{error::MikuError, tools, types::MikuType};
+
+/// The bytes every snapshot starts with.
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"MIKU";
+/// The version of the snapshot format written by this version of the vm.
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+/// Builds a snapshot.
+#[derive(Debug, Default)]
+pub struct SnapshotWriter {
+    bytes: Vec<u8>,
+}
+
+impl SnapshotWriter {
+    /// Creates a writer that already holds the magic and the version.
+    pub fn new() -> Self {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.extend(SNAPSHOT_VERSION.to_le_bytes());
+        Self { bytes }
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    pub fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    /// Writes a presence byte followed by the value if there is one.
+    pub fn option(&mut self, value: Option<u64>) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                self.u64(value);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    pub fn value(&mut self, value: MikuType) {
+        self.bytes.extend(Vec::from(value));
+    }
+
+    /// Writes the length of the bytes followed by the bytes.
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.usize(bytes.len());
+        self.bytes.extend(bytes);
+    }
+
+    /// Writes the bytes as they are.
+    pub fn raw(&mut self, bytes: &[u8]) {
+        self.bytes.extend(bytes);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Reads a snapshot.
+#[derive(Debug)]
+pub struct SnapshotReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SnapshotReader<'a> {
+    /// Checks the magic and the version and creates a reader positioned after them.
+    ///
+    /// # Returns
+    /// - `Ok(SnapshotReader)` if the bytes are a snapshot of the supported version.
+    /// - [`MikuError::InvalidSnapshot`] if the bytes don't start with the magic.
+    /// - [`MikuError::UnsupportedSnapshotVersion`] if the snapshot has another version.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, MikuError> {
+        if !bytes.starts_with(&SNAPSHOT_MAGIC) {
+            return Err(MikuError::InvalidSnapshot("MISSING MAGIC".to_string()));
+        }
+
+        let mut reader = Self { bytes, position: SNAPSHOT_MAGIC.len() };
+        let version = u16::from_le_bytes(tools::convert_bytes(reader.raw(size_of::<u16>())?)?);
+        if version != SNAPSHOT_VERSION {
+            return Err(MikuError::UnsupportedSnapshotVersion(version));
+        }
+
+        Ok(reader)
+    }
+
+    /// Reads the given number of bytes.
+    pub fn raw(&mut self, length: usize) -> Result<&'a [u8], MikuError> {
+        let bytes = self.position.checked_add(length)
+            .and_then(|end| self.bytes.get(self.position..end))
+            .ok_or(MikuError::InvalidSnapshot("UNEXPECTED END".to_string()))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, MikuError> {
+        Ok(self.raw(1)?[0])
+    }
+
+    pub fn u64(&mut self) -> Result<u64, MikuError> {
+        Ok(u64::from_le_bytes(tools::convert_bytes(self.raw(size_of::<u64>())?)?))
+    }
+
+    pub fn usize(&mut self) -> Result<usize, MikuError> {
+        let value = self.u64()?;
+        usize::try_from(value).map_err(|_| MikuError::InvalidSnapshot(format!("{} DOESN'T FIT INTO USIZE", value)))
+    }
+
+    /// Reads a presence byte followed by the value if there is one.
+    pub fn option(&mut self) -> Result<Option<u64>, MikuError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(self.u64()?)),
+            flag => Err(MikuError::InvalidSnapshot(format!("INVALID FLAG {}", flag))),
+        }
+    }
+
+    pub fn value(&mut self) -> Result<MikuType, MikuError> {
+        let type_id = *self.bytes.get(self.position).ok_or(MikuError::InvalidSnapshot("UNEXPECTED END".to_string()))?;
+        let length = MikuType::get_bytes_length(type_id)?;
+        MikuType::try_from(self.raw(length)?)
+    }
+
+    /// Reads a length followed by that many bytes.
+    pub fn bytes(&mut self) -> Result<&'a [u8], MikuError> {
+        let length = self.usize()?;
+        self.raw(length)
+    }
+
+    /// The bytes that haven't been read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.bytes[self.position..]
+    }
+
+    /// Checks that every byte has been read.
+    pub fn finish(self) -> Result<(), MikuError> {
+        match self.remaining().is_empty() {
+            true => Ok(()),
+            false => Err(MikuError::InvalidSnapshot("TRAILING BYTES".to_string())),
+        }
+    }
+}
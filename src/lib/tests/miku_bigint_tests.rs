@@ -0,0 +1,149 @@
+use crate::{bigint::BigInt, error::{MikuError, RuntimeError}, inst::*, miku::MikuVM, program::{decode_program, encode_program}, types::{MikuType, Pointer, Segment}, HEAP_END, HEAP_START};
+
+fn big(s: &str) -> BigInt {
+    s.parse().unwrap()
+}
+
+#[test]
+fn bigint_arithmetic_test() {
+    let a = big("123456789012345678901234567890123456789012345678901234567890");
+    let b = big("-987654321098765432109876543210");
+
+    assert_eq!(big("123456789012345678901234567889135802467913580246791358024680"), &a + &b);
+    assert_eq!(big("123456789012345678901234567891111111110111111111011111111100"), &a - &b);
+    assert_eq!(big("-121932631137021795226185032733744855963374485596337448559633622923332237463801111263526900"), &a * &b);
+    assert_eq!(big("-124999998860937500014238281249"), (&a / &b).unwrap());
+    assert_eq!(big("935329860093532986009353298600"), (&a % &b).unwrap());
+    assert_eq!(a, &(&(&a / &b).unwrap() * &b) + &(&a % &b).unwrap());
+
+    // Truncated division semantics like Rust's integers.
+    assert_eq!(BigInt::from(-7i128 / 2), (BigInt::from(-7i128) / BigInt::from(2i128)).unwrap());
+    assert_eq!(BigInt::from(-7i128 % 2), (BigInt::from(-7i128) % BigInt::from(2i128)).unwrap());
+    assert_eq!(BigInt::from(7i128 % -2), (BigInt::from(7i128) % BigInt::from(-2i128)).unwrap());
+    assert!(matches!(&a / &BigInt::zero(), Err(MikuError::DivisionByZeroError)));
+    assert!(matches!(&a % &BigInt::zero(), Err(MikuError::DivisionByZeroError)));
+
+    // Cancellation normalizes to zero.
+    assert!((&a - &a).is_zero());
+    assert!(!(&a - &a).is_negative());
+    assert_eq!("0", (&b - &b).to_string());
+}
+
+#[test]
+fn bigint_compare_and_convert_test() {
+    let values = [big(&format!("-1{}", "0".repeat(41))), BigInt::from(-1i128), BigInt::zero(), BigInt::from(u128::MAX), big("340282366920938463463374607431768211456")];
+    for window in values.windows(2) {
+        assert!(window[0] < window[1]);
+    }
+
+    assert_eq!(BigInt::from(69i128), BigInt::try_from(MikuType::U8(69)).unwrap());
+    assert_eq!(BigInt::from(i128::MIN), BigInt::try_from(MikuType::I128(i128::MIN)).unwrap());
+    assert!(BigInt::try_from(MikuType::F64(1.0)).is_err());
+    assert!(BigInt::try_from(MikuType::NULL).is_err());
+
+    assert_eq!(MikuType::I8(-128), BigInt::from(-128i128).to_miku_type(0x04).unwrap());
+    assert_eq!(MikuType::U64(u64::MAX), BigInt::from(u64::MAX as u128).to_miku_type(0x03).unwrap());
+    assert_eq!(MikuType::I128(i128::MIN), BigInt::from(i128::MIN).to_miku_type(0x0D).unwrap());
+    assert!(matches!(BigInt::from(-129i128).to_miku_type(0x04), Err(MikuError::IntegerConversionError(_))));
+    assert!(matches!(BigInt::from(-1i128).to_miku_type(0x0C), Err(MikuError::IntegerConversionError(_))));
+    assert!(matches!(values[4].to_miku_type(0x0C), Err(MikuError::IntegerConversionError(_))));
+    assert!(matches!(BigInt::zero().to_miku_type(0x08), Err(MikuError::UnknownTypeError(0x08))));
+
+    assert_eq!(format!("-1{}1", "0".repeat(40)), (&values[0] - &BigInt::from(1i128)).to_string());
+    assert!("12a".parse::<BigInt>().is_err());
+    assert!("-".parse::<BigInt>().is_err());
+    assert_eq!(BigInt::from(42i128), "+42".parse().unwrap());
+}
+
+#[test]
+fn vm_bigint_test() {
+    let mut vm = MikuVM::new();
+    let value = big("-123456789012345678901234567890123456789012345678901234567890");
+
+    // The block holds the signed limb count and the limbs.
+    let ptr = vm.alloc_bigint(&value).unwrap();
+    let limbs = value.limbs().len();
+    assert_eq!(MikuType::I64(-(limbs as i64)), vm.deref_ptr(ptr).unwrap());
+    assert_eq!(MikuType::U32(value.limbs()[0]), vm.deref_ptr(vm.offset_ptr(ptr, 1).unwrap()).unwrap());
+    assert_eq!(Some(limbs as u64), vm.bigint_limbs(ptr));
+    assert_eq!(value, vm.read_bigint(ptr).unwrap());
+    let zero = vm.alloc_bigint(&BigInt::zero()).unwrap();
+    assert!(vm.read_bigint(zero).unwrap().is_zero());
+
+    // Error cases
+    let block = vm.alloc(2).unwrap();
+    assert!(matches!(vm.read_bigint(block), Err(MikuError::InvalidBigInt(_))));
+    vm.write_ptr(block, MikuType::I64(1)).unwrap();
+    assert!(matches!(vm.read_bigint(block), Err(MikuError::InvalidBigInt(_))));
+    let last = vm.offset_ptr(MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_START as u64)), (HEAP_END - HEAP_START - 1) as i64).unwrap();
+    vm.write_ptr(last, MikuType::I64(i64::MIN)).unwrap();
+    assert!(matches!(vm.read_bigint(last), Err(MikuError::SegmentationFault)));
+    assert!(matches!(vm.read_bigint(MikuType::U64(0)), Err(MikuError::InvalidPointerType(_))));
+}
+
+#[test]
+fn bigint_inst_test() {
+    // (u128::MAX * u128::MAX - -3) % u128::MAX
+    let program: Vec<Box<dyn Inst>> = vec![
+        Box::new(Push::new(MikuType::U128(u128::MAX))),
+        Box::new(ToBig::new()),
+        Box::new(Push::new(MikuType::U128(u128::MAX))),
+        Box::new(ToBig::new()),
+        Box::new(BigMult::new()),
+        Box::new(Push::new(MikuType::I8(-3))),
+        Box::new(ToBig::new()),
+        Box::new(BigMinus::new()),
+        Box::new(Push::new(MikuType::U128(u128::MAX))),
+        Box::new(ToBig::new()),
+        Box::new(BigRem::new()),
+        Box::new(FromBig::new(0x00)),
+        Box::new(Push::new(MikuType::I64(-5))),
+        Box::new(ToBig::new()),
+        Box::new(Push::new(MikuType::U8(2))),
+        Box::new(ToBig::new()),
+        Box::new(BigCmp::new()),
+    ];
+    let bytes = encode_program(&program);
+    assert_eq!(bytes, encode_program(&decode_program(&bytes).unwrap()));
+    assert_eq!("bigmult", program[4].disassemble());
+    assert_eq!("frombig u8", program[11].disassemble());
+
+    let mut vm = MikuVM::from_program(program);
+    vm.run_program().unwrap();
+    assert_eq!(vec![MikuType::U8(3), MikuType::I8(-1)], vm.stack()[0..2].to_vec());
+
+    // Results are allocated, so they're charged like allocations of the largest possible result.
+    let mut vm = MikuVM::new();
+    vm.stack_push(MikuType::U128(u128::MAX)).unwrap();
+    assert_eq!(5 + 5, ToBig::new().gas_cost(&vm));
+    let ptr = vm.alloc_bigint(&BigInt::from(u128::MAX)).unwrap();
+    vm.stack_pop().unwrap();
+    vm.stack_push(ptr).unwrap();
+    vm.stack_push(ptr).unwrap();
+    assert_eq!(5 + 9, BigMult::new().gas_cost(&vm));
+    assert_eq!(5 + 6, BigPlus::new().gas_cost(&vm));
+
+    // Error cases
+    let error = |program: Vec<Box<dyn Inst>>| MikuVM::from_program(program).run_program().map_err(RuntimeError::into_error).unwrap_err();
+    assert!(matches!(error(vec![
+        Box::new(Push::new(MikuType::U16(256))),
+        Box::new(ToBig::new()),
+        Box::new(FromBig::new(0x00)),
+    ]), MikuError::IntegerConversionError(_)));
+    assert!(matches!(error(vec![
+        Box::new(Push::new(MikuType::U16(256))),
+        Box::new(ToBig::new()),
+        Box::new(Push::new(MikuType::U16(0))),
+        Box::new(ToBig::new()),
+        Box::new(BigDiv::new()),
+    ]), MikuError::DivisionByZeroError));
+    assert!(matches!(error(vec![
+        Box::new(Push::new(MikuType::F32(1.0))),
+        Box::new(ToBig::new()),
+    ]), MikuError::IntegerConversionError(_)));
+    assert!(matches!(error(vec![
+        Box::new(Push::new(MikuType::U64(1))),
+        Box::new(Push::new(MikuType::U64(1))),
+        Box::new(BigPlus::new()),
+    ]), MikuError::InvalidPointerType(_)));
+}
@@ -1,4 +1,4 @@
-use crate::{inst::*, miku::MikuVM, types::MikuType, DATA_START};
+use crate::{error::{MikuError, RuntimeError}, inst::*, layout::{Field, StructLayout}, miku::MikuVM, types::{MikuType, Pointer, Promotion, Segment}, DATA_START, HEAP_START};
 
 #[test]
 fn push_test() {
@@ -7,9 +7,9 @@ fn push_test() {
     let i1: Box<dyn Inst> = Box::new(Push::new(MikuType::U8(69)));
     let i2: Box<dyn Inst> = Box::new(Push::new(MikuType::I64(-728463721)));
     let i3: Box<dyn Inst> = Box::new(Push::new(MikuType::F32(8947.2932)));
-    vm.push_inst(&i1);
-    vm.push_inst(&i2);
-    vm.push_inst(&i3);
+    vm.push_inst(i1);
+    vm.push_inst(i2);
+    vm.push_inst(i3);
     let _ = vm.run_program();
     assert_eq!(
         vec![MikuType::U8(69), MikuType::I64(-728463721), MikuType::F32(8947.2932)], 
@@ -20,9 +20,9 @@ fn push_test() {
     assert_eq!(0, vm.stack_base());
 
     // Encoding test
-    let encoded_i1 = i1.encode();
+    let encoded_i1 = vm.program()[0].encode();
     assert_eq!(vec![0x00, 0x00, 0x45], encoded_i1);
-    let encoded_i2 = i2.encode();
+    let encoded_i2 = vm.program()[1].encode();
     assert_eq!(vec![0x00, 0x07, 0x97, 0x86, 0x94, 0xD4, 0xFF, 0xFF, 0xFF, 0xFF], encoded_i2);
 
     // Decoding test
@@ -43,11 +43,11 @@ fn pop_test() {
     let i1: Box<dyn Inst> = Box::new(Push::new(MikuType::U8(69)));
     let i2: Box<dyn Inst> = Box::new(Push::new(MikuType::I64(-728463721)));
     let i3: Box<dyn Inst> = Box::new(Push::new(MikuType::F32(8947.2932)));
-    vm.push_inst(&i1);
-    vm.push_inst(&i2);
-    vm.push_inst(&i3);
+    vm.push_inst(i1);
+    vm.push_inst(i2);
+    vm.push_inst(i3);
     let i4: Box<dyn Inst> = Box::new(Pop::new());
-    vm.push_inst(&i4);
+    vm.push_inst(i4);
     let _ = vm.run_program();
     assert_eq!(
         vec![MikuType::U8(69), MikuType::I64(-728463721), MikuType::F32(8947.2932)],
@@ -62,13 +62,13 @@ fn pop_test() {
     let i1: Box<dyn Inst> = Box::new(Push::new(MikuType::U8(69)));
     let i2: Box<dyn Inst> = Box::new(Push::new(MikuType::I64(-728463721)));
     let i3: Box<dyn Inst> = Box::new(Push::new(MikuType::F32(8947.2932)));
-    vm.push_inst(&i1);
-    vm.push_inst(&i2);
-    vm.push_inst(&i3);
+    vm.push_inst(i1);
+    vm.push_inst(i2);
+    vm.push_inst(i3);
     let i4: Box<dyn Inst> = Box::new(Pop::new());
     let i5: Box<dyn Inst> = Box::new(Push::new(MikuType::I16(420)));
-    vm.push_inst(&i4);
-    vm.push_inst(&i5);
+    vm.push_inst(i4);
+    vm.push_inst(i5);
     let _ = vm.run_program();
     assert_eq!(
         vec![MikuType::U8(69), MikuType::I64(-728463721), MikuType::I16(420)],
@@ -81,12 +81,12 @@ fn pop_test() {
     // Stack underflow test
     let mut vm = MikuVM::new();
     let i1: Box<dyn Inst> = Box::new(Pop::new());
-    vm.push_inst(&i1);
+    vm.push_inst(i1);
     let status = vm.run_program();
     assert!(status.is_err());
 
     // Encoding test
-    assert_eq!(vec![0x01], i4.encode());
+    assert_eq!(vec![0x01], Pop::new().encode());
 
     // Decoding test
     assert_eq!(Pop::new(), Pop::decode(&vec![0x01]).unwrap());
@@ -98,8 +98,8 @@ fn def_test() {
     let mut vm = MikuVM::new();
     let i1: Box<dyn Inst> = Box::new(Def::new(MikuType::U32(420), DATA_START));
     let i2: Box<dyn Inst> = Box::new(Def::new(MikuType::U8(69), DATA_START + 1));
-    vm.push_inst(&i1);
-    vm.push_inst(&i2);
+    vm.push_inst(i1);
+    vm.push_inst(i2);
     let status = vm.run_program();
     assert!(status.is_ok());
     assert_eq!(
@@ -119,3 +119,221 @@ fn def_test() {
         Def::decode(&vec![0x02, 0x00, 0x45, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]).unwrap()
     );
 }
+
+#[test]
+fn struct_test() {
+    let layout = StructLayout::new(
+        "point",
+        vec![Field::new("x", MikuType::I32(0).type_id(), 0), Field::new("y", MikuType::F64(0.0).type_id(), 1)],
+    ).unwrap();
+    let record = MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_START as u64));
+
+    // Functionality test
+    let mut vm = MikuVM::new();
+    let i1: Box<dyn Inst> = Box::new(DefStruct::new(0, layout.clone()));
+    let i2: Box<dyn Inst> = Box::new(Push::new(record));
+    let i3: Box<dyn Inst> = Box::new(Push::new(MikuType::I32(-7)));
+    let i4: Box<dyn Inst> = Box::new(FieldSet::new(0, 0));
+    let i5: Box<dyn Inst> = Box::new(Push::new(record));
+    let i6: Box<dyn Inst> = Box::new(Push::new(MikuType::F64(4.5)));
+    let i7: Box<dyn Inst> = Box::new(FieldSet::new(0, 1));
+    let i8: Box<dyn Inst> = Box::new(Push::new(record));
+    let i9: Box<dyn Inst> = Box::new(FieldGet::new(0, 1));
+    for inst in [i1, i2, i3, i4, i5, i6, i7, i8, i9] {
+        vm.push_inst(inst);
+    }
+    assert!(vm.run_program().is_ok());
+    assert_eq!(vec![MikuType::F64(4.5)], vm.stack()[0..1].to_vec());
+    assert_eq!(vec![MikuType::I32(-7), MikuType::F64(4.5)], vm.heap_mem()[0..2].to_vec());
+    assert_eq!(1, vm.stack_top());
+
+    // Field type mismatch test
+    let mut vm = MikuVM::new();
+    let i1: Box<dyn Inst> = Box::new(DefStruct::new(0, layout.clone()));
+    let i2: Box<dyn Inst> = Box::new(Push::new(record));
+    let i3: Box<dyn Inst> = Box::new(Push::new(MikuType::U8(1)));
+    let i4: Box<dyn Inst> = Box::new(FieldSet::new(0, 0));
+    vm.push_inst(i1);
+    vm.push_inst(i2);
+    vm.push_inst(i3);
+    vm.push_inst(i4);
+    assert!(matches!(vm.run_program().map_err(RuntimeError::into_error), Err(MikuError::FieldTypeMismatch(0x06, MikuType::U8(1)))));
+
+    // Undefined struct and field tests
+    let mut vm = MikuVM::new();
+    assert!(matches!(vm.get_field(record, 0, 0), Err(MikuError::UndefinedStruct(0))));
+    vm.define_struct(0, layout.clone()).unwrap();
+    assert!(matches!(vm.get_field(record, 0, 2), Err(MikuError::UndefinedField(0, 2))));
+    assert!(matches!(vm.get_field(record, 0, 0), Err(MikuError::FieldTypeMismatch(0x06, MikuType::NULL))));
+    assert!(matches!(vm.define_struct(0, layout.clone()), Err(MikuError::StructRedefinition(0))));
+
+    // Invalid layout test
+    assert!(StructLayout::new("p", vec![Field::new("x", 0x00, 0), Field::new("x", 0x00, 1)]).is_err());
+    assert!(StructLayout::new("p", vec![Field::new("x", 0x00, 0), Field::new("y", 0x00, 0)]).is_err());
+    assert!(StructLayout::new("p", vec![Field::new("x", 0xFF, 0)]).is_err());
+
+    // Encoding and decoding test
+    let def_struct = DefStruct::new(3, layout);
+    assert_eq!(def_struct, DefStruct::decode(&def_struct.encode()).unwrap());
+    assert_eq!(FieldGet::new(1, 2), FieldGet::decode(&FieldGet::new(1, 2).encode()).unwrap());
+    assert_eq!(FieldSet::new(3, 4), FieldSet::decode(&FieldSet::new(3, 4).encode()).unwrap());
+    assert!(FieldGet::decode(&[0x04, 0x00]).is_err());
+}
+
+#[test]
+fn arith_test() {
+    // Functionality test
+    let mut vm = MikuVM::new();
+    let i1: Box<dyn Inst> = Box::new(Push::new(MikuType::I32(20)));
+    let i2: Box<dyn Inst> = Box::new(Push::new(MikuType::I32(4)));
+    let i3: Box<dyn Inst> = Box::new(Div::new());
+    let i4: Box<dyn Inst> = Box::new(Push::new(MikuType::I32(3)));
+    let i5: Box<dyn Inst> = Box::new(Minus::new());
+    let i6: Box<dyn Inst> = Box::new(Push::new(MikuType::I32(7)));
+    let i7: Box<dyn Inst> = Box::new(Mult::new());
+    let i8: Box<dyn Inst> = Box::new(Push::new(MikuType::I32(1)));
+    let i9: Box<dyn Inst> = Box::new(Plus::new());
+    for inst in [i1, i2, i3, i4, i5, i6, i7, i8, i9] {
+        vm.push_inst(inst);
+    }
+    assert!(vm.run_program().is_ok());
+    assert_eq!(MikuType::I32(15), vm.stack()[0]);
+    assert_eq!(1, vm.stack_top());
+
+    // Strict promotion test
+    let mut vm = MikuVM::new();
+    let i1: Box<dyn Inst> = Box::new(Push::new(MikuType::U8(200)));
+    let i2: Box<dyn Inst> = Box::new(Push::new(MikuType::I8(-100)));
+    let i3: Box<dyn Inst> = Box::new(Plus::new());
+    vm.push_inst(i1);
+    vm.push_inst(i2);
+    vm.push_inst(i3);
+    assert!(matches!(vm.run_program().map_err(RuntimeError::into_error), Err(MikuError::UndefinedOperationBetweenTypesError(_))));
+
+    // Widening promotion test
+    let mut vm = MikuVM::new();
+    vm.set_promotion(Promotion::Widening);
+    vm.load_program(vec![
+        Box::new(Push::new(MikuType::U8(200))),
+        Box::new(Push::new(MikuType::I8(-100))),
+        Box::new(Plus::new()),
+    ]);
+    assert!(vm.run_program().is_ok());
+    assert_eq!(MikuType::I16(100), vm.stack()[0]);
+
+    // Overflow test
+    let overflows = [
+        (MikuType::U8(255), MikuType::U8(1), Box::new(Plus::new()) as Box<dyn Inst>),
+        (MikuType::U64(0), MikuType::U64(1), Box::new(Minus::new())),
+        (MikuType::I64(i64::MAX), MikuType::I64(2), Box::new(Mult::new())),
+        (MikuType::U128(u128::MAX), MikuType::U128(1), Box::new(Plus::new())),
+        (MikuType::I128(i128::MIN), MikuType::I128(1), Box::new(Minus::new())),
+        (MikuType::I32(i32::MIN), MikuType::I32(-1), Box::new(Div::new())),
+        (MikuType::I128(i128::MIN), MikuType::I128(-1), Box::new(Div::new())),
+    ];
+    for (lhs, rhs, inst) in overflows {
+        let mut vm = MikuVM::from_program(vec![Box::new(Push::new(lhs)), Box::new(Push::new(rhs)), inst]);
+        assert!(matches!(vm.run_program().map_err(RuntimeError::into_error), Err(MikuError::ArithmeticOverflow(_))));
+    }
+    assert_eq!(Ok(MikuType::F64(f64::INFINITY)), (MikuType::F64(f64::MAX) * MikuType::F64(2.0)).map_err(|e| e.to_string()));
+
+    // Encoding and decoding test
+    assert_eq!(vec![0x09], Div::new().encode());
+    assert_eq!(Plus::new(), Plus::decode(&[0x06]).unwrap());
+    assert!(Mult::decode(&[0x08, 0x00]).is_err());
+}
+
+#[test]
+fn load_store_test() {
+    // Functionality test
+    let mut vm = MikuVM::new();
+    vm.set_byte_memory(8);
+    let i1: Box<dyn Inst> = Box::new(Push::new(MikuType::Ptr(Pointer::new(Segment::Bytes, 4))));
+    let i2: Box<dyn Inst> = Box::new(Push::new(MikuType::I32(-420)));
+    let i3: Box<dyn Inst> = Box::new(Store::new());
+    let i4: Box<dyn Inst> = Box::new(Push::new(MikuType::Ptr(Pointer::new(Segment::Bytes, 4))));
+    let i5: Box<dyn Inst> = Box::new(Load::new(0x06));
+    for inst in [i1, i2, i3, i4, i5] {
+        vm.push_inst(inst);
+    }
+    assert!(vm.run_program().is_ok());
+    assert_eq!(MikuType::I32(-420), vm.stack()[0]);
+    assert_eq!(1, vm.stack_top());
+
+    // Encoding and decoding test
+    assert_eq!(vec![0x0B], Store::new().encode());
+    assert_eq!(Load::new(0x09), Load::decode(&Load::new(0x09).encode()).unwrap());
+    assert_eq!(Store::new(), Store::decode(&[0x0B]).unwrap());
+    assert!(Load::decode(&[0x0A]).is_err());
+}
+
+#[test]
+fn alloc_free_test() {
+    // Functionality test
+    let mut vm = MikuVM::new();
+    let i1: Box<dyn Inst> = Box::new(Push::new(MikuType::U64(4)));
+    let i2: Box<dyn Inst> = Box::new(Alloc::new());
+    let i3: Box<dyn Inst> = Box::new(Push::new(MikuType::U64(2)));
+    let i4: Box<dyn Inst> = Box::new(Alloc::new());
+    let i5: Box<dyn Inst> = Box::new(Free::new());
+    for inst in [i1, i2, i3, i4, i5] {
+        vm.push_inst(inst);
+    }
+    assert!(vm.run_program().is_ok());
+    assert_eq!(1, vm.stack_top());
+    assert_eq!(MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_START as u64)), vm.stack()[0]);
+    assert_eq!(vec![(HEAP_START, 4)], vm.heap().blocks().collect::<Vec<_>>());
+
+    // Freeing a value that isn't a pointer
+    let mut vm = MikuVM::new();
+    let i1: Box<dyn Inst> = Box::new(Push::new(MikuType::U64(4)));
+    let i2: Box<dyn Inst> = Box::new(Free::new());
+    vm.push_inst(i1);
+    vm.push_inst(i2);
+    assert!(matches!(vm.run_program().map_err(RuntimeError::into_error), Err(MikuError::InvalidPointerType(_))));
+
+    // Encoding and decoding test
+    assert_eq!(vec![0x0C], Alloc::new().encode());
+    assert_eq!(vec![0x0D], Free::new().encode());
+    assert_eq!(Alloc::new(), Alloc::decode(&[0x0C]).unwrap());
+    assert_eq!(Free::new(), Free::decode(&[0x0D]).unwrap());
+    assert!(Free::decode(&[0x0D, 0x00]).is_err());
+}
+
+#[test]
+fn disassemble_test() {
+    let point = StructLayout::new("point", vec![Field::new("x", 0x06, 0), Field::new("y", 0x06, 1)]).unwrap();
+    let program: Vec<Box<dyn Inst>> = vec![
+        Box::new(Push::new(MikuType::U8(69))),
+        Box::new(Pop::new()),
+        Box::new(Def::new(MikuType::F64(-1.5), DATA_START)),
+        Box::new(DefStruct::new(0, point)),
+        Box::new(FieldGet::new(0, 1)),
+        Box::new(FieldSet::new(0, 0)),
+        Box::new(Plus::new()),
+        Box::new(Div::new()),
+        Box::new(Load::new(0x0C)),
+        Box::new(Load::new(0xEE)),
+        Box::new(Store::new()),
+        Box::new(Alloc::new()),
+        Box::new(Free::new()),
+    ];
+    assert_eq!(
+        vec![
+            "push u8 69".to_string(),
+            "pop".to_string(),
+            format!("def f64 -1.5 {}", DATA_START),
+            "defstruct 0 point { x: i32 @ 0, y: i32 @ 1 }".to_string(),
+            "fget 0 1".to_string(),
+            "fset 0 0".to_string(),
+            "plus".to_string(),
+            "div".to_string(),
+            "load u128".to_string(),
+            "load ?".to_string(),
+            "store".to_string(),
+            "alloc".to_string(),
+            "free".to_string(),
+        ],
+        program.iter().map(|inst| inst.disassemble()).collect::<Vec<_>>()
+    );
+}
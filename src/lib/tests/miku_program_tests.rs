@@ -0,0 +1,210 @@
+use crate::{error::{MikuError, RuntimeError}, trap::{TrapAction, TrapFn, TrapKind}, inst::*, layout::{Field, StructLayout}, miku::MikuVM, program::{decode_program, decode_program_lenient, encode_extended, encode_program, extended_payload, InstRegistry, Program}, types::{MikuType, Pointer, Segment}, DATA_START, HEAP_START};
+
+#[test]
+fn round_trip_test() {
+    let layout = StructLayout::new("point", vec![Field::new("x", 0x06, 0), Field::new("y", 0x06, 1)]).unwrap();
+    let program: Vec<Box<dyn Inst>> = vec![
+        Box::new(Def::new(MikuType::I128(-5), DATA_START)),
+        Box::new(DefStruct::new(1, layout)),
+        Box::new(Push::new(MikuType::Ptr(Pointer::new(Segment::Heap, 700)))),
+        Box::new(Push::new(MikuType::NULL)),
+        Box::new(Pop::new()),
+        Box::new(FieldGet::new(1, 0)),
+        Box::new(FieldSet::new(1, 1)),
+        Box::new(Plus::new()),
+        Box::new(Minus::new()),
+        Box::new(Mult::new()),
+        Box::new(Div::new()),
+        Box::new(Load::new(0x0C)),
+        Box::new(Store::new()),
+        Box::new(Alloc::new()),
+        Box::new(Free::new()),
+    ];
+
+    let bytes = encode_program(&program);
+    let decoded = decode_program(&bytes).unwrap();
+    assert_eq!(program.len(), decoded.len());
+    for (inst, decoded_inst) in program.iter().zip(&decoded) {
+        assert_eq!(inst.encode(), decoded_inst.encode());
+    }
+    assert_eq!(bytes, encode_program(&decoded));
+    assert!(decode_program(&[]).unwrap().is_empty());
+}
+
+#[test]
+fn decode_and_run_test() {
+    let program: Vec<Box<dyn Inst>> = vec![
+        Box::new(Push::new(MikuType::U16(400))),
+        Box::new(Push::new(MikuType::U16(20))),
+        Box::new(Plus::new()),
+    ];
+
+    let mut vm = MikuVM::from_program(decode_program(&encode_program(&program)).unwrap());
+    assert!(vm.run_program().is_ok());
+    assert_eq!(MikuType::U16(420), vm.stack()[0]);
+}
+
+#[test]
+fn invalid_program_test() {
+    assert!(matches!(decode_program(&[0x01, 0xFE]), Err(MikuError::UnknownOpcode(0xFE))));
+    assert!(matches!(decode_program(&[0x00]), Err(MikuError::BytesConversionError)));
+    assert!(matches!(decode_program(&[0x00, 0x03, 0x01]), Err(MikuError::BytesConversionError)));
+    assert!(matches!(decode_program(&[0x00, 0x42]), Err(MikuError::UnknownTypeError(0x42))));
+    assert!(matches!(decode_program(&[0x0A]), Err(MikuError::BytesConversionError)));
+    assert!(matches!(decode_program(&[0x04, 0x01, 0x00]), Err(MikuError::BytesConversionError)));
+    assert!(matches!(decode_program(&[0x03, 0x01]), Err(MikuError::BytesConversionError)));
+}
+
+/// A custom instruction that pushes the top of the stack the given number of times.
+#[derive(Debug, PartialEq)]
+struct Dup {
+    count: u8,
+}
+
+impl Inst for Dup {
+    fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+        vm.inc_pc();
+        let top = vm.stack_pop()?;
+        for _ in 0..=self.count {
+            vm.stack_push(top)?;
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        encode_extended(0x0100, &[self.count])
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+        match extended_payload(bytes)? {
+            [count] => Ok(Dup { count: *count }),
+            _ => Err(MikuError::BytesConversionError),
+        }
+    }
+}
+
+#[test]
+fn registry_test() {
+    let program: Vec<Box<dyn Inst>> = vec![
+        Box::new(Push::new(MikuType::U8(7))),
+        Box::new(Dup { count: 2 }),
+        Box::new(Pop::new()),
+    ];
+    let bytes = encode_program(&program);
+    assert_eq!(vec![0xFF, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x02], bytes[3..11].to_vec());
+
+    // Custom instructions have to be registered.
+    assert!(matches!(decode_program(&bytes), Err(MikuError::UnknownExtendedOpcode(0x0100))));
+
+    let mut registry = InstRegistry::new();
+    assert!(registry.register::<Dup>(0x0100).is_ok());
+    assert!(registry.is_registered(0x0100));
+    assert!(matches!(registry.register::<Dup>(0x0100), Err(MikuError::OpcodeAlreadyRegistered(0x0100))));
+
+    // Round trip and execution
+    let decoded = registry.decode_program(&bytes).unwrap();
+    assert_eq!(bytes, encode_program(&decoded));
+    let mut vm = MikuVM::from_program(decoded);
+    assert!(vm.run_program().is_ok());
+    assert_eq!(2, vm.stack_top());
+    assert_eq!(vec![MikuType::U8(7), MikuType::U8(7)], vm.stack()[0..2].to_vec());
+
+    // Truncated custom instructions
+    assert!(matches!(registry.decode_program(&[0xFF, 0x00]), Err(MikuError::BytesConversionError)));
+    assert!(matches!(registry.decode_program(&[0xFF, 0x00, 0x01, 0x02, 0x00, 0x00, 0x00, 0x02]), Err(MikuError::BytesConversionError)));
+    assert!(matches!(registry.decode_program(&[0xFF, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00]), Err(MikuError::BytesConversionError)));
+}
+
+#[test]
+fn lenient_decode_test() {
+    let program: Vec<Box<dyn Inst>> = vec![
+        Box::new(Push::new(MikuType::U8(7))),
+        Box::new(Dup { count: 2 }),
+        Box::new(Pop::new()),
+    ];
+    let bytes = encode_program(&program);
+
+    // Unknown custom instructions are decoded and fail when they're executed.
+    let decoded = decode_program_lenient(&bytes).unwrap();
+    assert_eq!(3, decoded.len());
+    assert_eq!("unknown extended 256", decoded[1].disassemble());
+    assert_eq!(bytes, encode_program(&decoded));
+
+    let mut vm = MikuVM::from_program(decoded);
+    let error = vm.run_program().unwrap_err();
+    assert!(matches!(error.error(), MikuError::UnknownExtendedOpcode(0x0100)));
+    assert_eq!(1, error.pc());
+
+    // A trap handler can skip them.
+    let mut vm = MikuVM::from_program(decode_program_lenient(&bytes).unwrap());
+    vm.set_trap_handler(TrapKind::UnknownOpcode, Box::new(TrapFn::new(|_, error: &RuntimeError| {
+        assert!(matches!(error.error(), MikuError::UnknownExtendedOpcode(0x0100)));
+        TrapAction::Skip
+    })));
+    assert!(vm.run_program().is_ok());
+    assert_eq!(0, vm.stack_top());
+
+    // An unknown built-in opcode takes up the rest of the program.
+    let mut bytes = encode_program(&[Box::new(Push::new(MikuType::U8(7))) as Box<dyn Inst>]);
+    bytes.extend([0xFE, 0x01, 0x02]);
+    assert!(matches!(decode_program(&bytes), Err(MikuError::UnknownOpcode(0xFE))));
+    let decoded = decode_program_lenient(&bytes).unwrap();
+    assert_eq!(2, decoded.len());
+    assert_eq!("unknown 254", decoded[1].disassemble());
+    assert_eq!(bytes, encode_program(&decoded));
+    let mut vm = MikuVM::from_program(decoded);
+    assert!(matches!(vm.run_program().map_err(RuntimeError::into_error), Err(MikuError::UnknownOpcode(0xFE))));
+
+    // Truncated instructions still fail.
+    assert!(matches!(decode_program_lenient(&[0xFF, 0x00, 0x01, 0x02]), Err(MikuError::BytesConversionError)));
+}
+
+#[test]
+fn shared_program_test() {
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Send + Sync>() {}
+    assert_send::<MikuVM>();
+    assert_sync::<Program>();
+
+    // One program executed by many vms in parallel.
+    let program = Program::from(vec![
+        Box::new(Push::new(MikuType::U64(2))) as Box<dyn Inst>,
+        Box::new(Alloc::new()),
+        Box::new(Push::new(MikuType::I32(-7))),
+    ]);
+    let stacks = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                let program = program.clone();
+                scope.spawn(move || {
+                    let mut vm = MikuVM::new();
+                    vm.set_program(program);
+                    vm.run_program().unwrap();
+                    vm.stack()[0..2].to_vec()
+                })
+            })
+            .collect();
+        workers.into_iter().map(|worker| worker.join().unwrap()).collect::<Vec<_>>()
+    });
+    let heap = MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_START as u64));
+    assert!(stacks.iter().all(|stack| *stack == vec![heap, MikuType::I32(-7)]));
+
+    // A paused vm can be moved to another thread and continue there.
+    let mut vm = MikuVM::new();
+    vm.set_program(program.clone());
+    vm.step();
+    let vm = std::thread::spawn(move || {
+        vm.run_program().unwrap();
+        vm
+    }).join().unwrap();
+    assert_eq!(3, vm.pc());
+
+    // Pushing onto a shared program leaves the other copies untouched.
+    let mut vm = MikuVM::new();
+    vm.set_program(program.clone());
+    vm.push_inst(Box::new(Pop::new()));
+    assert_eq!(4, vm.program().len());
+    assert_eq!(3, program.len());
+    assert_eq!(program.encode(), encode_program(&vm.program()[..3]));
+    assert_eq!(3, Program::decode(&program.encode()).unwrap().len());
+}
@@ -0,0 +1,150 @@
+use crate::{config::VmConfig, error::MikuError, heap::GcStats, inst::*, program::{encode_extended, extended_payload, InstRegistry}, layout::{Field, StructLayout}, miku::{MikuVM, Status}, snapshot::{SNAPSHOT_MAGIC, SNAPSHOT_VERSION}, types::{Access, MikuType, Pointer, Promotion, Segment}};
+
+fn program() -> Vec<Box<dyn Inst>> {
+    let layout = StructLayout::new("pair", vec![Field::new("a", 0x02, 0), Field::new("b", 0x02, 1)]).unwrap();
+    vec![
+        Box::new(Def::new(MikuType::U32(10), 16)),
+        Box::new(DefStruct::new(3, layout)),
+        Box::new(Push::new(MikuType::U64(2))),
+        Box::new(Alloc::new()),
+        Box::new(Push::new(MikuType::U32(7))),
+        Box::new(FieldSet::new(3, 1)),
+        Box::new(Push::new(MikuType::U64(2))),
+        Box::new(Alloc::new()),
+        Box::new(Pop::new()),
+        Box::new(Push::new(MikuType::Ptr(Pointer::new(Segment::Bytes, 0)))),
+        Box::new(Push::new(MikuType::I16(-4))),
+        Box::new(Store::new()),
+        Box::new(Push::new(MikuType::U8(5))),
+        Box::new(Push::new(MikuType::I16(6))),
+        Box::new(Plus::new()),
+    ]
+}
+
+fn new_vm() -> MikuVM {
+    let config = VmConfig::builder()
+        .memory_size(64)
+        .stack_size(16)
+        .data_size(8)
+        .byte_memory_size(8)
+        .gc_threshold(32)
+        .gas_limit(1000)
+        .build()
+        .unwrap();
+    let mut vm = MikuVM::with_config(config);
+    vm.set_promotion(Promotion::Widening);
+    vm.load_program(program());
+    vm
+}
+
+#[test]
+fn resume_test() {
+    let mut uninterrupted = new_vm();
+    uninterrupted.run_program().unwrap();
+
+    // Snapshots taken at every instruction resume to the same result.
+    for pc in 0..=program().len() {
+        let mut vm = new_vm();
+        vm.run_for(pc);
+        let snapshot = vm.snapshot();
+        drop(vm);
+
+        let mut restored = MikuVM::restore(&snapshot).unwrap();
+        assert_eq!(pc, restored.pc());
+        assert_eq!(snapshot, restored.snapshot());
+        assert!(restored.run_program().is_ok());
+        assert_eq!(uninterrupted.snapshot(), restored.snapshot());
+    }
+
+    assert_eq!(MikuType::I16(11), uninterrupted.stack()[uninterrupted.stack_top() - 1]);
+    assert_eq!(Access::ReadOnly, uninterrupted.access(Segment::Data));
+    assert_eq!(&[0xFC, 0xFF], &uninterrupted.byte_memory().bytes()[0..2]);
+    assert!(uninterrupted.struct_layout(3).is_some());
+    assert_eq!(GcStats { live_blocks: 2, live_cells: 4, ..Default::default() }, uninterrupted.gc_stats());
+    assert_eq!(Some(1000 - 35), uninterrupted.gas());
+}
+
+#[test]
+fn invalid_snapshot_test() {
+    let snapshot = new_vm().snapshot();
+    assert_eq!(SNAPSHOT_MAGIC, snapshot[0..4]);
+
+    let mut other_version = snapshot.clone();
+    other_version[4..6].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+    assert!(matches!(MikuVM::restore(&other_version), Err(MikuError::UnsupportedSnapshotVersion(2))));
+
+    assert!(matches!(MikuVM::restore(b"MIKA\x01\x00"), Err(MikuError::InvalidSnapshot(_))));
+    assert!(matches!(MikuVM::restore(&snapshot[..snapshot.len() - 1]), Err(MikuError::InvalidSnapshot(_))));
+    let mut trailing = snapshot.clone();
+    trailing.push(0);
+    assert!(matches!(MikuVM::restore(&trailing), Err(MikuError::InvalidSnapshot(_))));
+    assert!(MikuVM::restore(&snapshot).is_ok());
+
+    // Sizes are checked against the snapshot before anything is allocated.
+    let mut huge_memory = snapshot.clone();
+    huge_memory[6..14].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+    assert!(matches!(MikuVM::restore(&huge_memory), Err(MikuError::InvalidSnapshot(_))));
+    let mut huge_byte_memory = snapshot.clone();
+    huge_byte_memory[30..38].copy_from_slice(&(1u64 << 40).to_le_bytes());
+    assert!(matches!(MikuVM::restore(&huge_byte_memory), Err(MikuError::InvalidSnapshot(_))));
+
+    // The heap statistics have to match the heap blocks.
+    let mut vm = new_vm();
+    vm.run_for(4);
+    let snapshot = vm.snapshot();
+    let program_length = vm.program().encode().len();
+    let stats_start = snapshot.len() - program_length - 8 - 5 * 8;
+    assert_eq!(1u64.to_le_bytes(), snapshot[stats_start + 3 * 8..stats_start + 4 * 8]);
+    let mut zeroed_stats = snapshot.clone();
+    zeroed_stats[stats_start..stats_start + 5 * 8].fill(0);
+    assert!(matches!(MikuVM::restore(&zeroed_stats), Err(MikuError::InvalidSnapshot(_))));
+    assert_eq!(1, MikuVM::restore(&snapshot).unwrap().gc_stats().live_blocks);
+
+    // A paused vm can be snapshotted too.
+    let mut vm = new_vm();
+    vm.add_breakpoint(4);
+    assert!(matches!(vm.run(), Status::Paused(_)));
+    assert_eq!(4, MikuVM::restore(&vm.snapshot()).unwrap().pc());
+}
+
+/// A custom instruction that negates the [`MikuType::I32`] on top of the stack.
+#[derive(Debug)]
+struct Neg;
+
+impl Inst for Neg {
+    fn execute(&self, vm: &mut MikuVM) -> Result<(), MikuError> {
+        vm.inc_pc();
+        let value = vm.stack_pop()?;
+        vm.stack_push((MikuType::I32(0) - value)?)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        encode_extended(0x0200, &[])
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, MikuError> where Self: Sized {
+        extended_payload(bytes)?;
+        Ok(Neg)
+    }
+}
+
+#[test]
+fn custom_inst_snapshot_test() {
+    let mut vm = MikuVM::from_program(vec![
+        Box::new(Push::new(MikuType::I32(5))),
+        Box::new(Neg),
+        Box::new(Neg),
+    ]);
+    vm.step();
+    let snapshot = vm.snapshot();
+
+    // Restoring needs a registry that knows the custom instruction.
+    assert!(matches!(MikuVM::restore(&snapshot), Err(MikuError::UnknownExtendedOpcode(0x0200))));
+
+    let mut registry = InstRegistry::new();
+    registry.register::<Neg>(0x0200).unwrap();
+    let mut restored = MikuVM::restore_with_registry(&snapshot, &registry).unwrap();
+    assert_eq!(snapshot, restored.snapshot());
+    restored.run_program().unwrap();
+    assert_eq!(MikuType::I32(5), restored.stack()[0]);
+}
@@ -1,4 +1,6 @@
-use super::super::types::MikuType;
+use std::cmp::Ordering;
+
+use super::super::{error::{MikuError, ParseTypeError}, types::{MikuType, Pointer, Promotion, Segment}};
 
 #[test]
 fn addition_test() {
@@ -118,6 +120,10 @@ fn convert_to_bytes_test() {
     let a_f64 = MikuType::F64(-8909374798909789.590);
     let a_f64_vec = Vec::from(a_f64);
     assert_eq!(&vec![0x09, 0x5E, 0x51, 0x0F, 0x78, 0x07, 0xA7, 0x3F, 0xC3], &a_f64_vec);
+
+    let a_ptr = MikuType::Ptr(Pointer::new(Segment::Heap, 700));
+    let a_ptr_vec = Vec::from(a_ptr);
+    assert_eq!(&vec![0x0B, 0x02, 0xBC, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], &a_ptr_vec);
 }
 
 #[test]
@@ -161,4 +167,162 @@ fn convert_from_bytes_test() {
     // F64
     let a_f64_vec = vec![0x09, 0x5E, 0x51, 0x0F, 0x78, 0x07, 0xA7, 0x3F, 0xC3];
     assert_eq!(MikuType::try_from(&a_f64_vec[..]).unwrap(), MikuType::F64(-8909374798909789.590));
+
+    // Ptr
+    let a_ptr_vec = vec![0x0B, 0x02, 0xBC, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+    assert_eq!(MikuType::try_from(&a_ptr_vec[..]).unwrap(), MikuType::Ptr(Pointer::new(Segment::Heap, 700)));
+    assert_eq!(10, MikuType::get_bytes_length(0x0B).unwrap());
+    let bad_segment_vec = vec![0x0B, 0x07, 0xBC, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+    assert!(MikuType::try_from(&bad_segment_vec[..]).is_err());
+
+    // Pointers don't take part in arithmetic.
+    assert!((MikuType::Ptr(Pointer::new(Segment::Heap, 700)) + MikuType::U64(1)).is_err());
+}
+
+#[test]
+fn comparison_test() {
+    assert!(MikuType::U8(3) < MikuType::U8(4));
+    assert!(MikuType::I64(-1) < MikuType::I64(0));
+    assert!(MikuType::F64(1.5) > MikuType::F64(-1.5));
+    assert!(MikuType::Ptr(Pointer::new(Segment::Heap, 700)) < MikuType::Ptr(Pointer::new(Segment::Heap, 701)));
+
+    // Different variants are unordered unless compared numerically.
+    assert_eq!(None, MikuType::U8(3).partial_cmp(&MikuType::I64(3)));
+    assert_eq!(None, MikuType::Ptr(Pointer::new(Segment::Stack, 0)).partial_cmp(&MikuType::Ptr(Pointer::new(Segment::Heap, 700))));
+    assert!(MikuType::U8(3).numeric_eq(&MikuType::I64(3)));
+    assert!(MikuType::U64(u64::MAX).numeric_cmp(&MikuType::I64(-1)) == Some(Ordering::Greater));
+    assert!(MikuType::I8(-2).numeric_cmp(&MikuType::F32(-1.5)) == Some(Ordering::Less));
+    assert_eq!(None, MikuType::F64(f64::NAN).numeric_cmp(&MikuType::U8(0)));
+    assert_eq!(None, MikuType::NULL.numeric_cmp(&MikuType::U8(0)));
+
+    // Total ordering
+    let mut values = vec![MikuType::F64(f64::NAN), MikuType::I8(-1), MikuType::NULL, MikuType::U8(2), MikuType::U8(1), MikuType::F64(-0.5)];
+    values.sort_by(MikuType::total_cmp);
+    assert_eq!(MikuType::U8(1), values[0]);
+    assert_eq!(MikuType::U8(2), values[1]);
+    assert_eq!(MikuType::I8(-1), values[2]);
+    assert_eq!(MikuType::F64(-0.5), values[3]);
+    assert!(matches!(values[4], MikuType::F64(value) if value.is_nan()));
+    assert_eq!(MikuType::NULL, values[5]);
+
+    // Zero checks used by division
+    assert!(MikuType::U64(0).is_zero());
+    assert!(MikuType::F32(-0.0).is_zero());
+    assert!(!MikuType::NULL.is_zero());
+    assert!((MikuType::F64(1.0) / MikuType::F64(0.0)).is_err());
+}
+
+#[test]
+fn promotion_test() {
+    let strict = MikuType::U8(1).promote(MikuType::I64(2), Promotion::Strict).unwrap();
+    assert_eq!((MikuType::U8(1), MikuType::I64(2)), strict);
+
+    let promote = |lhs: MikuType, rhs: MikuType| lhs.promote(rhs, Promotion::Widening).unwrap();
+    assert_eq!((MikuType::U32(1), MikuType::U32(2)), promote(MikuType::U8(1), MikuType::U32(2)));
+    assert_eq!((MikuType::I64(-1), MikuType::I64(2)), promote(MikuType::I64(-1), MikuType::I16(2)));
+    assert_eq!((MikuType::I16(200), MikuType::I16(-1)), promote(MikuType::U8(200), MikuType::I8(-1)));
+    assert_eq!((MikuType::I64(-1), MikuType::I64(7)), promote(MikuType::I32(-1), MikuType::U32(7)));
+    assert_eq!((MikuType::I128(5), MikuType::I128(7)), promote(MikuType::U64(5), MikuType::I8(7)));
+
+    // Unsigned operands keep their value.
+    assert_eq!((MikuType::I128(u64::MAX as i128), MikuType::I128(1)), promote(MikuType::U64(u64::MAX), MikuType::I8(1)));
+    assert_eq!((MikuType::I128(-1), MikuType::I128(u64::MAX as i128)), promote(MikuType::I64(-1), MikuType::U64(u64::MAX)));
+    let (lhs, rhs) = promote(MikuType::U64(u64::MAX), MikuType::I8(1));
+    assert_eq!(MikuType::I128(u64::MAX as i128 + 1), (lhs + rhs).unwrap());
+    assert!(matches!(MikuType::U128(u128::MAX).promote(MikuType::I8(1), Promotion::Widening), Err(MikuError::ArithmeticOverflow(_))));
+    assert!(matches!(MikuType::I64(-1).promote(MikuType::U128(u128::MAX), Promotion::Widening), Err(MikuError::ArithmeticOverflow(_))));
+    assert_eq!((MikuType::I128(i128::MAX), MikuType::I128(1)), promote(MikuType::U128(i128::MAX as u128), MikuType::I8(1)));
+    assert_eq!((MikuType::F32(1.5), MikuType::F32(2.0)), promote(MikuType::F32(1.5), MikuType::I32(2)));
+    assert_eq!((MikuType::F64(1.5), MikuType::F64(2.0)), promote(MikuType::F32(1.5), MikuType::F64(2.0)));
+    assert_eq!((MikuType::NULL, MikuType::U8(1)), promote(MikuType::NULL, MikuType::U8(1)));
+
+    assert_eq!(MikuType::U8(44), MikuType::I32(300).cast(0x00).unwrap());
+    assert_eq!(MikuType::I8(-3), MikuType::F64(-3.7).cast(0x04).unwrap());
+    assert!(MikuType::NULL.cast(0x00).is_err());
+    assert!(MikuType::U8(0).cast(0x0A).is_err());
+}
+
+#[test]
+fn parse_and_display_test() {
+    assert_eq!(Ok(MikuType::U8(69)), "u8 69".parse());
+    assert_eq!(Ok(MikuType::I32(-420)), "i32   -420".parse());
+    assert_eq!(Ok(MikuType::U16(0xFFFF)), "u16 0xFFFF".parse());
+    assert_eq!(Ok(MikuType::I8(-5)), "i8 -0b101".parse());
+    assert_eq!(Ok(MikuType::U32(15)), "u32 0o17".parse());
+    assert_eq!(Ok(MikuType::F64(-1500.0)), "f64 -1.5e3".parse());
+    assert_eq!(Ok(MikuType::F32(0.25)), "f32 0.25".parse());
+    assert_eq!(Ok(MikuType::NULL), "null".parse());
+    assert_eq!(Ok(MikuType::Ptr(Pointer::new(Segment::Data, 310))), "ptr data 310".parse());
+
+    // Error cases
+    assert_eq!(Err(ParseTypeError::InvalidLiteral("u8".to_string(), "256".to_string())), "u8 256".parse::<MikuType>());
+    assert_eq!(Err(ParseTypeError::InvalidLiteral("u8".to_string(), "-1".to_string())), "u8 -1".parse::<MikuType>());
+    assert_eq!(Err(ParseTypeError::InvalidLiteral("i16".to_string(), "--1".to_string())), "i16 --1".parse::<MikuType>());
+    assert_eq!(Err(ParseTypeError::InvalidLiteral("f32".to_string(), "abc".to_string())), "f32 abc".parse::<MikuType>());
+    assert_eq!(Err(ParseTypeError::UnknownType("u7".to_string())), "u7 1".parse::<MikuType>());
+    assert_eq!(Err(ParseTypeError::UnknownSegment("rom".to_string())), "ptr rom 1".parse::<MikuType>());
+    assert_eq!(Err(ParseTypeError::InvalidFormat("u8".to_string())), "u8".parse::<MikuType>());
+    assert_eq!(Err(ParseTypeError::InvalidFormat("".to_string())), "".parse::<MikuType>());
+
+    // Round trip
+    let values = [
+        MikuType::U8(69), MikuType::U16(420), MikuType::U32(u32::MAX), MikuType::U64(u64::MAX),
+        MikuType::I8(i8::MIN), MikuType::I16(-420), MikuType::I32(i32::MIN), MikuType::I64(i64::MIN),
+        MikuType::F32(89093747.5830423), MikuType::F64(-8909374798909789.590), MikuType::F64(1e300),
+        MikuType::F32(f32::INFINITY), MikuType::NULL, MikuType::Ptr(Pointer::new(Segment::Stack, 3)),
+    ];
+    for value in values {
+        assert_eq!(Ok(value), value.to_string().parse());
+    }
+    assert_eq!("u8 69", MikuType::U8(69).to_string());
+    assert_eq!("f64 -1500.0", MikuType::F64(-1500.0).to_string());
+    assert_eq!("ptr heap 700", MikuType::Ptr(Pointer::new(Segment::Heap, 700)).to_string());
+    assert!(matches!("f64 NaN".parse(), Ok(MikuType::F64(value)) if value.is_nan()));
+}
+
+#[test]
+fn wide_integer_test() {
+    // Encoding round trip
+    let a_u128 = MikuType::U128(u128::MAX - 1);
+    let a_u128_vec = Vec::from(a_u128);
+    assert_eq!(17, a_u128_vec.len());
+    assert_eq!(0x0C, a_u128_vec[0]);
+    assert_eq!(17, MikuType::get_bytes_length(0x0C).unwrap());
+    assert_eq!(a_u128, MikuType::try_from(&a_u128_vec[..]).unwrap());
+
+    let a_i128 = MikuType::I128(-2);
+    let a_i128_vec = Vec::from(a_i128);
+    assert_eq!(vec![0x0D, 0xFE], a_i128_vec[..2].to_vec());
+    assert!(a_i128_vec[2..].iter().all(|byte| *byte == 0xFF));
+    assert_eq!(17, MikuType::get_bytes_length(0x0D).unwrap());
+    assert_eq!(a_i128, MikuType::try_from(&a_i128_vec[..]).unwrap());
+
+    // Arithmetic
+    let big = u64::MAX as u128;
+    assert_eq!(MikuType::U128(big * 2), (MikuType::U128(big) + MikuType::U128(big)).unwrap());
+    assert_eq!(MikuType::I128(-(big as i128) * 3), (MikuType::I128(big as i128) * MikuType::I128(-3)).unwrap());
+    assert_eq!(MikuType::I128(-1), (MikuType::I128(1) - MikuType::I128(2)).unwrap());
+    assert!((MikuType::U128(1) / MikuType::U128(0)).is_err());
+    assert!((MikuType::U128(1) + MikuType::U64(1)).is_err());
+    assert_eq!(
+        (MikuType::I128(-1), MikuType::I128(big as i128)),
+        MikuType::I8(-1).promote(MikuType::U128(big), Promotion::Widening).unwrap()
+    );
+    assert_eq!(
+        (MikuType::I128(-1), MikuType::I128(7)),
+        MikuType::I128(-1).promote(MikuType::U32(7), Promotion::Widening).unwrap()
+    );
+    assert_eq!(MikuType::U8(0xFE), MikuType::U128(u128::MAX - 1).cast(0x00).unwrap());
+
+    // Comparison
+    assert!(MikuType::U128(u128::MAX).numeric_cmp(&MikuType::I128(i128::MAX)) == Some(Ordering::Greater));
+    assert!(MikuType::I8(0).numeric_cmp(&MikuType::U128(u128::MAX)) == Some(Ordering::Less));
+    assert!(MikuType::U128(5).numeric_eq(&MikuType::I64(5)));
+
+    // Parsing
+    assert_eq!(Ok(MikuType::U128(u128::MAX)), format!("u128 {}", u128::MAX).parse());
+    assert_eq!(Ok(MikuType::I128(i128::MIN)), format!("i128 {}", i128::MIN).parse());
+    assert_eq!(Ok(MikuType::U128(0xFFFF_FFFF_FFFF_FFFF_FFFF)), "u128 0xFFFFFFFFFFFFFFFFFFFF".parse());
+    assert!("i128 170141183460469231731687303715884105728".parse::<MikuType>().is_err());
+    assert_eq!("i128 -2", a_i128.to_string());
 }
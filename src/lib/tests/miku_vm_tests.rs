@@ -1,4 +1,4 @@
-use crate::{miku::MikuVM, types::MikuType, DATA_END, DATA_START, STACK_START};
+use crate::{config::VmConfig, debug::{Pause, WatchKind}, error::{MikuError, RuntimeError}, gas::GasTable, inst::*, json::Json, miku::{MikuVM, StackFrame, Status}, mmio::{Callbacks, Device}, observer::Observer, trap::{TrapAction, TrapFn, TrapKind}, types::{Access, MikuType, Pointer, Segment}, DATA_END, DATA_START, HEAP_END, HEAP_START, STACK_END, STACK_START};
 
 #[test]
 fn define_data_test() {
@@ -15,11 +15,11 @@ fn define_data_test() {
 fn deref_address_test() {
     let mut vm = MikuVM::new();
     let _ = vm.define_data(MikuType::U8(69), DATA_START);
-    let read_data = vm.deref_ptr(MikuType::U64(DATA_START as u64)).unwrap();
+    let read_data = vm.deref_ptr(MikuType::Ptr(Pointer::new(Segment::Data, DATA_START as u64))).unwrap();
     assert_eq!(MikuType::U8(69), read_data);
 
     let _ = vm.stack_push(MikuType::U64(0));
-    let read_data = vm.deref_ptr(MikuType::U64(STACK_START as u64)).unwrap();
+    let read_data = vm.deref_ptr(MikuType::Ptr(Pointer::new(Segment::Stack, STACK_START as u64))).unwrap();
     assert_eq!(MikuType::U64(0), read_data);
 
     let read_data = vm.deref_ptr(MikuType::NULL);
@@ -28,6 +28,832 @@ fn deref_address_test() {
     let read_data = vm.deref_ptr(MikuType::U32(0));
     assert!(read_data.is_err());
 
-    let read_data = vm.deref_ptr(MikuType::U64(1024));
+    let read_data = vm.deref_ptr(MikuType::U64(DATA_START as u64));
+    assert!(matches!(read_data, Err(MikuError::InvalidPointerType(MikuType::U64(_)))));
+
+    let read_data = vm.deref_ptr(MikuType::Ptr(Pointer::new(Segment::Heap, 1024)));
     assert!(read_data.is_err());
+
+    let read_data = vm.deref_ptr(MikuType::Ptr(Pointer::new(Segment::Stack, DATA_START as u64)));
+    assert!(matches!(read_data, Err(MikuError::SegmentationFault)));
+}
+
+#[test]
+fn pointer_test() {
+    let mut vm = MikuVM::new();
+    let heap = MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_START as u64));
+    vm.write_ptr(heap, MikuType::I16(-3)).unwrap();
+    let next = vm.offset_ptr(heap, 1).unwrap();
+    vm.write_ptr(next, MikuType::I16(4)).unwrap();
+    assert_eq!(vec![MikuType::I16(-3), MikuType::I16(4)], vm.heap_mem()[0..2].to_vec());
+    assert_eq!(MikuType::I16(-3), vm.deref_ptr(vm.offset_ptr(next, -1).unwrap()).unwrap());
+
+    // Pointer arithmetic can't leave the segment.
+    assert!(matches!(vm.offset_ptr(heap, -1), Err(MikuError::SegmentationFault)));
+    assert!(matches!(vm.offset_ptr(heap, (HEAP_END - HEAP_START) as i64), Err(MikuError::SegmentationFault)));
+    assert!(matches!(vm.offset_ptr(MikuType::U64(0), 1), Err(MikuError::InvalidPointerType(_))));
+    assert!(vm.write_ptr(MikuType::Ptr(Pointer::new(Segment::Data, HEAP_START as u64)), MikuType::U8(0)).is_err());
+}
+
+#[test]
+fn byte_memory_test() {
+    let mut vm = MikuVM::new();
+    assert!(matches!(vm.load_bytes(bytes(0), 0x00), Err(MikuError::SegmentationFault)));
+
+    vm.set_byte_memory(32);
+    vm.store_bytes(bytes(0), MikuType::U32(0xDEADBEEF)).unwrap();
+    vm.store_bytes(bytes(4), MikuType::I16(-2)).unwrap();
+    vm.store_bytes(bytes(6), MikuType::U8(7)).unwrap();
+    vm.store_bytes(bytes(8), MikuType::F64(1.5)).unwrap();
+    vm.store_bytes(bytes(16), MikuType::U128(u128::MAX)).unwrap();
+    assert_eq!(&[0xEF, 0xBE, 0xAD, 0xDE, 0xFE, 0xFF, 0x07, 0x00], &vm.byte_memory().bytes()[0..8]);
+
+    // Values are packed and can be reinterpreted with a different width.
+    assert_eq!(MikuType::U32(0xDEADBEEF), vm.load_bytes(bytes(0), 0x02).unwrap());
+    assert_eq!(MikuType::U16(0xBEEF), vm.load_bytes(bytes(0), 0x01).unwrap());
+    assert_eq!(MikuType::U64(0x0007_FFFE_DEAD_BEEF), vm.load_bytes(bytes(0), 0x03).unwrap());
+    assert_eq!(MikuType::I16(-2), vm.load_bytes(bytes(4), 0x05).unwrap());
+    assert_eq!(MikuType::F64(1.5), vm.load_bytes(bytes(8), 0x09).unwrap());
+    assert_eq!(MikuType::I128(-1), vm.load_bytes(bytes(16), 0x0D).unwrap());
+
+    // Error cases
+    assert!(matches!(vm.load_bytes(bytes(2), 0x02), Err(MikuError::MisalignedAccess(4, 2))));
+    assert!(matches!(vm.store_bytes(bytes(1), MikuType::I16(1)), Err(MikuError::MisalignedAccess(2, 1))));
+    assert!(matches!(vm.load_bytes(bytes(32), 0x00), Err(MikuError::SegmentationFault)));
+    assert!(matches!(vm.store_bytes(bytes(32), MikuType::U128(0)), Err(MikuError::SegmentationFault)));
+    assert!(matches!(vm.load_bytes(bytes(0), 0x0A), Err(MikuError::UnsupportedTypeError(0x0A))));
+    assert!(matches!(vm.store_bytes(bytes(0), MikuType::NULL), Err(MikuError::UnsupportedTypeError(0x0A))));
+    assert!(matches!(vm.load_bytes(MikuType::U64(0), 0x00), Err(MikuError::InvalidPointerType(_))));
+    assert!(matches!(vm.load_bytes(MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_START as u64)), 0x00), Err(MikuError::InvalidPointerType(_))));
+    assert!(matches!(vm.load_bytes(MikuType::NULL, 0x00), Err(MikuError::SegmentationFault)));
+    assert!(matches!(vm.deref_ptr(bytes(0)), Err(MikuError::SegmentationFault)));
+
+    // Byte pointers move by bytes and stay within the byte memory.
+    assert_eq!(bytes(6), vm.offset_ptr(bytes(4), 2).unwrap());
+    assert!(matches!(vm.offset_ptr(bytes(31), 1), Err(MikuError::SegmentationFault)));
+}
+
+#[test]
+fn byte_memory_hooks_test() {
+    let mut vm = MikuVM::new();
+    vm.set_byte_memory(16);
+
+    // Permissions
+    vm.set_access(Segment::Bytes, Access::ReadOnly);
+    assert!(matches!(vm.store_bytes(bytes(0), MikuType::U8(1)), Err(MikuError::ProtectionFault(Segment::Bytes))));
+    assert_eq!(MikuType::U8(0), vm.load_bytes(bytes(0), 0x00).unwrap());
+    vm.set_access(Segment::Bytes, Access::ReadWrite);
+
+    // Watchpoints see the bytes of an access and ignore the cells with the same addresses.
+    vm.load_program(vec![
+        Box::new(Push::new(bytes(4))),
+        Box::new(Push::new(MikuType::U32(9))),
+        Box::new(Store::new()),
+        Box::new(Push::new(bytes(8))),
+        Box::new(Load::new(0x03)),
+    ]);
+    vm.add_watchpoint(6..7, WatchKind::Write);
+    let id = vm.add_byte_watchpoint(6..7, WatchKind::ReadWrite);
+    assert!(matches!(vm.run(), Status::Paused(Pause::Watchpoint { id: hit, address: 6, access: WatchKind::Write }) if hit == id));
+
+    // Mapped devices
+    let device = Callbacks::new(|offset| Ok(MikuType::U64(offset as u64 + 8)), |_, _| Err(MikuError::SegmentationFault));
+    assert!(matches!(vm.map_byte_device(8..24, Box::new(Callbacks::new(|_| Ok(MikuType::NULL), |_, _| Ok(())))), Err(MikuError::InvalidMapping(8, 24))));
+    vm.map_byte_device(8..16, Box::new(device)).unwrap();
+    assert!(matches!(vm.run(), Status::Halted));
+    assert_eq!(MikuType::U64(8), vm.stack()[0]);
+    assert_eq!(&[0; 8], &vm.byte_memory().bytes()[8..16]);
+    assert!(matches!(vm.load_bytes(bytes(8), 0x02), Err(MikuError::DeviceTypeMismatch(0x02, MikuType::U64(8)))));
+    assert!(matches!(vm.store_bytes(bytes(8), MikuType::U8(1)), Err(MikuError::SegmentationFault)));
+
+    assert!(vm.unmap_byte_device(12).is_some());
+    vm.store_bytes(bytes(8), MikuType::U8(1)).unwrap();
+    assert_eq!(MikuType::U8(1), vm.load_bytes(bytes(8), 0x00).unwrap());
+}
+
+fn bytes(offset: u64) -> MikuType {
+    MikuType::Ptr(Pointer::new(Segment::Bytes, offset))
+}
+
+#[test]
+fn config_test() {
+    // The default layout has no gaps between the segments.
+    let vm = MikuVM::new();
+    assert_eq!(STACK_END, vm.layout().segment(Segment::Data).start);
+    assert_eq!(DATA_END, vm.layout().segment(Segment::Heap).start);
+    assert_eq!(HEAP_END, vm.layout().memory_size());
+    assert_eq!(MikuVM::with_config(VmConfig::builder().build().unwrap()).layout(), vm.layout());
+
+    let config = VmConfig::builder()
+        .memory_size(16)
+        .stack_size(2)
+        .data_size(4)
+        .byte_memory_size(8)
+        .build()
+        .unwrap();
+    let mut vm = MikuVM::with_config(config);
+    assert_eq!(0..2, vm.layout().segment(Segment::Stack));
+    assert_eq!(2..6, vm.layout().segment(Segment::Data));
+    assert_eq!(6..16, vm.layout().segment(Segment::Heap));
+    assert_eq!(Some(Segment::Data), vm.layout().segment_of(5));
+    assert_eq!(None, vm.layout().segment_of(16));
+    assert_eq!(8, vm.byte_memory().len());
+
+    // The layout is used by every memory access.
+    assert!(vm.stack_push(MikuType::U8(1)).is_ok());
+    assert!(vm.stack_push(MikuType::U8(2)).is_ok());
+    assert!(matches!(vm.stack_push(MikuType::U8(3)), Err(MikuError::StackOverflow)));
+    assert!(vm.define_data(MikuType::U8(4), 5).is_ok());
+    assert!(matches!(vm.define_data(MikuType::U8(4), 6), Err(MikuError::SegmentationFault)));
+    assert_eq!(MikuType::U8(4), vm.deref_ptr(MikuType::Ptr(Pointer::new(Segment::Data, 5))).unwrap());
+    assert!(vm.deref_ptr(MikuType::Ptr(Pointer::new(Segment::Heap, 16))).is_err());
+    assert_eq!(10, vm.heap_mem().len());
+
+    // Invalid configurations
+    assert!(matches!(VmConfig::builder().memory_size(0).build(), Err(MikuError::InvalidConfig(_))));
+    assert!(matches!(VmConfig::builder().stack_size(0).build(), Err(MikuError::InvalidConfig(_))));
+    assert!(matches!(VmConfig::builder().memory_size(8).stack_size(4).data_size(5).build(), Err(MikuError::InvalidConfig(_))));
+    assert!(matches!(VmConfig::builder().stack_size(usize::MAX).data_size(1).build(), Err(MikuError::InvalidConfig(_))));
+    assert!(VmConfig::builder().memory_size(8).stack_size(4).data_size(4).build().is_ok());
+
+    // Huge memories don't overflow while the layout is computed.
+    let config = VmConfig::builder().memory_size(usize::MAX / 2).stack_size(1).data_size(1).build().unwrap();
+    assert_eq!(2..usize::MAX / 2, config.layout().segment(Segment::Heap));
+    let config = VmConfig::builder().memory_size(usize::MAX).build().unwrap();
+    assert_eq!(usize::MAX / 10 * 3, config.layout().segment(Segment::Stack).len());
+}
+
+#[test]
+fn gc_test() {
+    let config = VmConfig::builder()
+        .memory_size(16)
+        .stack_size(4)
+        .data_size(2)
+        .build()
+        .unwrap();
+    let mut vm = MikuVM::with_config(config);
+
+    // Allocation and manual freeing
+    let a = vm.alloc(3).unwrap();
+    assert_eq!(MikuType::Ptr(Pointer::new(Segment::Heap, 6)), a);
+    let b = vm.alloc(2).unwrap();
+    assert_eq!(MikuType::Ptr(Pointer::new(Segment::Heap, 9)), b);
+    assert!(vm.free(a).is_ok());
+    assert!(matches!(vm.free(a), Err(MikuError::InvalidFree(6))));
+    assert!(matches!(vm.free(MikuType::Ptr(Pointer::new(Segment::Heap, 10))), Err(MikuError::InvalidFree(10))));
+    assert!(matches!(vm.free(MikuType::U64(9)), Err(MikuError::InvalidPointerType(_))));
+    assert_eq!(MikuType::Ptr(Pointer::new(Segment::Heap, 6)), vm.alloc(1).unwrap());
+    assert!(matches!(vm.alloc(8), Err(MikuError::OutOfMemory(8))));
+    assert_eq!(2, vm.gc_stats().live_blocks);
+
+    // Nothing is reachable from the roots.
+    assert_eq!(2, vm.collect_garbage());
+    assert_eq!(0, vm.gc_stats().live_cells);
+
+    // Blocks reachable from the stack, the .data segment or another reachable block are kept.
+    let root = vm.alloc(2).unwrap();
+    let child = vm.alloc(1).unwrap();
+    let garbage = vm.alloc(1).unwrap();
+    let data_root = vm.alloc(1).unwrap();
+    vm.write_ptr(root, child).unwrap();
+    vm.write_ptr(child, MikuType::U8(69)).unwrap();
+    vm.write_ptr(garbage, MikuType::U8(42)).unwrap();
+    vm.stack_push(root).unwrap();
+    vm.define_data(data_root, 4).unwrap();
+    assert_eq!(1, vm.collect_garbage());
+    assert_eq!(MikuType::U8(69), vm.deref_ptr(child).unwrap());
+    assert_eq!(MikuType::NULL, vm.deref_ptr(garbage).unwrap());
+    assert_eq!(vec![(6, 2), (8, 1), (10, 1)], vm.heap().blocks().collect::<Vec<_>>());
+
+    let stats = vm.gc_stats();
+    assert_eq!(2, stats.collections);
+    assert_eq!(3, stats.freed_blocks);
+    assert_eq!(4, stats.freed_cells);
+    assert_eq!(3, stats.live_blocks);
+
+    // Automatic collection once the threshold is exceeded.
+    vm.stack_pop().unwrap();
+    vm.set_gc_threshold(Some(4));
+    assert!(vm.alloc(1).is_ok());
+    assert_eq!(3, vm.gc_stats().collections);
+    assert_eq!(vec![(6, 1), (10, 1)], vm.heap().blocks().collect::<Vec<_>>());
+}
+
+#[test]
+fn gc_huge_alloc_test() {
+    // Huge sizes from the program fail instead of overflowing the threshold check.
+    let mut vm = MikuVM::with_config(VmConfig::builder().gc_threshold(4).build().unwrap());
+    vm.load_program(vec![
+        Box::new(Push::new(MikuType::U64(1))),
+        Box::new(Alloc::new()),
+        Box::new(Push::new(MikuType::U64(u64::MAX))),
+        Box::new(Alloc::new()),
+    ]);
+    let error = vm.run_program().unwrap_err();
+    assert!(matches!(error.error(), MikuError::OutOfMemory(_)));
+    assert_eq!(3, error.pc());
+    assert!(vm.alloc(usize::MAX).is_err());
+}
+
+#[test]
+fn protection_test() {
+    // The .data segment is sealed once the initialisation phase is over.
+    let mut vm = MikuVM::new();
+    let i1: Box<dyn Inst> = Box::new(Def::new(MikuType::U8(69), DATA_START));
+    let i2: Box<dyn Inst> = Box::new(Push::new(MikuType::U8(1)));
+    vm.push_inst(i1);
+    vm.push_inst(i2);
+    assert_eq!(Access::ReadWrite, vm.access(Segment::Data));
+    assert!(vm.run_program().is_ok());
+    assert_eq!(Access::ReadOnly, vm.access(Segment::Data));
+    let constant = MikuType::Ptr(Pointer::new(Segment::Data, DATA_START as u64));
+    assert!(matches!(vm.write_ptr(constant, MikuType::U8(0)), Err(MikuError::ProtectionFault(Segment::Data))));
+    assert!(matches!(vm.define_data(MikuType::U8(0), DATA_START + 1), Err(MikuError::ProtectionFault(Segment::Data))));
+    assert_eq!(MikuType::U8(69), vm.deref_ptr(constant).unwrap());
+
+    // Definitions after the initialisation phase fault.
+    let mut vm = MikuVM::new();
+    vm.load_program(vec![
+        Box::new(Push::new(MikuType::U8(1))),
+        Box::new(Def::new(MikuType::U8(42), DATA_START + 1)),
+    ]);
+    assert!(matches!(vm.run_program().map_err(RuntimeError::into_error), Err(MikuError::ProtectionFault(Segment::Data))));
+
+    // Permissions can be set for every segment.
+    let mut vm = MikuVM::new();
+    vm.set_access(Segment::Heap, Access::ReadOnly);
+    assert!(matches!(vm.alloc(1), Err(MikuError::ProtectionFault(Segment::Heap))));
+    assert!(vm.write_ptr(MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_START as u64)), MikuType::U8(0)).is_err());
+    vm.set_access(Segment::Stack, Access::ReadOnly);
+    assert!(matches!(vm.stack_push(MikuType::U8(0)), Err(MikuError::ProtectionFault(Segment::Stack))));
+    vm.seal_data();
+    vm.set_access(Segment::Data, Access::ReadWrite);
+    assert!(vm.define_data(MikuType::U8(0), DATA_START).is_ok());
+}
+
+#[derive(Debug, Default)]
+struct Registers {
+    cells: [u64; 2],
+    writes: usize,
+}
+
+impl Device for Registers {
+    fn read(&mut self, offset: usize) -> Result<MikuType, MikuError> {
+        Ok(MikuType::U64(self.cells[offset]))
+    }
+
+    fn write(&mut self, offset: usize, value: MikuType) -> Result<(), MikuError> {
+        match value {
+            MikuType::U64(value) => self.cells[offset] = value,
+            _ => return Err(MikuError::UnsupportedTypeError(value.type_id())),
+        }
+        self.writes += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn mmio_test() {
+    let mut vm = MikuVM::new();
+    let registers = MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_START as u64));
+    assert!(vm.map_device(HEAP_START..HEAP_START + 2, Box::new(Registers::default())).is_ok());
+
+    // Reads and writes are routed to the device.
+    vm.write_ptr(vm.offset_ptr(registers, 1).unwrap(), MikuType::U64(69)).unwrap();
+    assert_eq!(MikuType::U64(69), vm.deref_ptr(vm.offset_ptr(registers, 1).unwrap()).unwrap());
+    assert_eq!(MikuType::U64(0), vm.deref_ptr(registers).unwrap());
+    assert_eq!(MikuType::NULL, vm.heap_mem()[1]);
+    assert!(matches!(vm.write_ptr(registers, MikuType::U8(1)), Err(MikuError::UnsupportedTypeError(0x00))));
+
+    // Mapped heap cells are never allocated.
+    assert_eq!(MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_START as u64 + 2)), vm.alloc(1).unwrap());
+
+    // Invalid mappings
+    assert!(matches!(vm.map_device(HEAP_START + 1..HEAP_START + 3, Box::new(Registers::default())), Err(MikuError::InvalidMapping(_, _))));
+    assert!(vm.map_device(HEAP_START + 2..HEAP_START + 3, Box::new(Registers::default())).is_err());
+    assert!(vm.map_device(STACK_START..STACK_START + 1, Box::new(Registers::default())).is_err());
+    assert!(vm.map_device(DATA_END - 1..DATA_END + 1, Box::new(Registers::default())).is_err());
+    assert!(vm.map_device(DATA_START..DATA_START, Box::new(Registers::default())).is_err());
+
+    // Devices in the .data segment are reached by definitions too.
+    let mut log = Vec::new();
+    let console = Callbacks::new(|_| Ok(MikuType::NULL), move |_, value| { log.push(value); Ok(()) });
+    assert!(vm.map_device(DATA_START..DATA_START + 1, Box::new(console)).is_ok());
+    assert!(vm.define_data(MikuType::U8(1), DATA_START).is_ok());
+    assert!(vm.define_data(MikuType::U8(2), DATA_START).is_ok());
+    assert_eq!(MikuType::NULL, vm.data_mem()[0]);
+
+    // Unmapping gives the device back.
+    assert!(vm.unmap_device(HEAP_START + 1).is_some());
+    assert!(vm.unmap_device(HEAP_START + 1).is_none());
+    assert_eq!(MikuType::NULL, vm.deref_ptr(registers).unwrap());
+    assert_eq!(MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_START as u64)), vm.alloc(2).unwrap());
+}
+
+fn build_vm() -> MikuVM {
+    let mut vm = MikuVM::from_program(vec![Box::new(Push::new(MikuType::U8(1)))]);
+    vm.push_inst(Box::new(Push::new(MikuType::U8(2))));
+    vm
+}
+
+#[test]
+fn program_test() {
+    // The vm owns its program so it can be returned from functions.
+    let mut vm = build_vm();
+    assert_eq!(2, vm.program().len());
+    assert!(vm.run_program().is_ok());
+    assert_eq!(vec![MikuType::U8(1), MikuType::U8(2)], vm.stack()[0..2].to_vec());
+    assert_eq!(2, vm.program().len());
+
+    // Loading a program resets the program counter.
+    vm.load_program(vec![Box::new(Pop::new())]);
+    assert_eq!(0, vm.pc());
+    assert!(vm.run_program().is_ok());
+    assert_eq!(1, vm.stack_top());
+}
+
+#[test]
+fn step_test() {
+    let program = || -> Vec<Box<dyn Inst>> {
+        vec![
+            Box::new(Push::new(MikuType::U8(1))),
+            Box::new(Push::new(MikuType::U8(2))),
+            Box::new(Push::new(MikuType::U8(3))),
+            Box::new(Pop::new()),
+            Box::new(Pop::new()),
+        ]
+    };
+
+    // Single steps
+    let mut vm = MikuVM::from_program(program());
+    assert!(vm.step().is_running());
+    assert_eq!(1, vm.pc());
+    assert_eq!(1, vm.stack_top());
+
+    // Bounded execution
+    assert!(matches!(vm.run_for(2), Status::Running));
+    assert_eq!(3, vm.pc());
+    assert!(matches!(vm.run_for(5), Status::Halted));
+    assert_eq!(5, vm.pc());
+    assert!(matches!(vm.step(), Status::Halted));
+    assert!(matches!(vm.run_for(0), Status::Halted));
+
+    // Running until a program counter
+    let mut vm = MikuVM::from_program(program());
+    assert!(matches!(vm.run_until(3), Status::Running));
+    assert_eq!(3, vm.pc());
+    assert_eq!(3, vm.stack_top());
+    assert!(matches!(vm.run_until(3), Status::Halted));
+
+    // Traps
+    let mut vm = MikuVM::from_program(vec![Box::new(Pop::new()), Box::new(Pop::new())]);
+    assert!(matches!(vm.step(), Status::Trapped(error) if matches!(error.error(), MikuError::StackUnderflow)));
+    assert_eq!(1, vm.pc());
+    assert!(matches!(MikuVM::new().step(), Status::Halted));
+}
+
+#[test]
+fn gas_test() {
+    let program = || -> Vec<Box<dyn Inst>> {
+        vec![
+            Box::new(Push::new(MikuType::U64(4))),
+            Box::new(Alloc::new()),
+            Box::new(Pop::new()),
+        ]
+    };
+
+    // Execution isn't metered by default.
+    let mut vm = MikuVM::from_program(program());
+    assert!(vm.run_program().is_ok());
+    assert_eq!(None, vm.gas());
+    vm.add_gas(10);
+    assert_eq!(None, vm.gas());
+
+    // push (1) + alloc (5 + 4 cells) + pop (1)
+    let mut vm = MikuVM::with_config(VmConfig::builder().gas_limit(20).build().unwrap());
+    vm.load_program(program());
+    assert!(vm.run_program().is_ok());
+    assert_eq!(Some(9), vm.gas());
+
+    // Allocated cells are charged before the allocation runs, so it can be retried.
+    let mut vm = MikuVM::from_program(program());
+    vm.set_gas(Some(8));
+    assert!(matches!(vm.run_for(3), Status::Trapped(error) if matches!(error.error(), MikuError::OutOfGas(9))));
+    assert_eq!(1, vm.pc());
+    assert_eq!(1, vm.stack_top());
+    assert_eq!(Some(7), vm.gas());
+    assert_eq!(0, vm.heap().stats().live_blocks);
+    vm.add_gas(100);
+    assert!(matches!(vm.run(), Status::Halted));
+    assert_eq!(1, vm.heap().stats().live_blocks);
+    assert_eq!(Some(97), vm.gas());
+
+    // Allocations made by the host aren't metered.
+    let mut vm = MikuVM::new();
+    vm.set_gas(Some(0));
+    assert!(vm.alloc(4).is_ok());
+    assert_eq!(Some(0), vm.gas());
+
+    // Built-in instructions don't have to be encoded to find their opcode.
+    assert_eq!(Alloc::new().encode()[0], Alloc::new().opcode());
+    assert_eq!(Plus::new().encode()[0], Plus::new().opcode());
+
+    // The instruction that runs out of gas isn't executed and can be retried.
+    let mut vm = MikuVM::from_program(program());
+    vm.set_gas(Some(3));
+    assert!(matches!(vm.step(), Status::Running));
+    assert!(matches!(vm.step(), Status::Trapped(error) if matches!(error.error(), MikuError::OutOfGas(9))));
+    assert_eq!(1, vm.pc());
+    vm.add_gas(8);
+    assert!(matches!(vm.run_for(2), Status::Halted));
+
+    // Custom cost tables
+    let mut table = GasTable::flat();
+    table.set_cost(0x01, 10);
+    assert_eq!(0, table.alloc_cell_cost());
+    let mut vm = MikuVM::from_program(program());
+    vm.set_gas_table(table);
+    vm.set_gas(Some(11));
+    assert!(matches!(vm.run_for(3), Status::Trapped(error) if matches!(error.error(), MikuError::OutOfGas(10))));
+    assert!(vm.charge_gas(9).is_ok());
+    assert!(vm.charge_gas(1).is_err());
+}
+
+#[test]
+fn debug_test() {
+    let program = || -> Vec<Box<dyn Inst>> {
+        vec![
+            Box::new(Def::new(MikuType::U8(9), DATA_START)),
+            Box::new(Push::new(MikuType::U8(1))),
+            Box::new(Push::new(MikuType::U8(2))),
+            Box::new(Pop::new()),
+            Box::new(Pop::new()),
+        ]
+    };
+
+    // Breakpoints pause before the instruction and are skipped when resuming.
+    let mut vm = MikuVM::from_program(program());
+    assert!(vm.add_breakpoint(2));
+    assert!(!vm.add_breakpoint(2));
+    assert!(matches!(vm.run(), Status::Paused(Pause::Breakpoint(2))));
+    assert_eq!(2, vm.pc());
+    assert_eq!(1, vm.stack_top());
+    assert!(matches!(vm.run(), Status::Halted));
+    assert!(vm.remove_breakpoint(2));
+    assert!(!vm.remove_breakpoint(2));
+
+    // Watchpoints pause after the instruction that accessed the address.
+    let mut vm = MikuVM::from_program(program());
+    let data = vm.add_watchpoint(DATA_START..DATA_START + 1, WatchKind::Write);
+    let second = vm.add_watchpoint(STACK_START + 1..STACK_START + 2, WatchKind::Read);
+    assert!(matches!(vm.run(), Status::Paused(Pause::Watchpoint { id, address: DATA_START, access: WatchKind::Write }) if id == data));
+    assert_eq!(1, vm.pc());
+    assert!(matches!(vm.run(), Status::Paused(Pause::Watchpoint { id, address: 1, access: WatchKind::Read }) if id == second));
+    assert_eq!(4, vm.pc());
+    assert!(matches!(vm.run(), Status::Halted));
+
+    // Both kinds of access
+    let mut vm = MikuVM::from_program(program());
+    vm.add_watchpoint(STACK_START..STACK_END, WatchKind::ReadWrite);
+    assert!(matches!(vm.run_for(5), Status::Paused(Pause::Watchpoint { address: 0, access: WatchKind::Write, .. })));
+    assert_eq!(2, vm.pc());
+    assert!(matches!(vm.run_until(5), Status::Paused(Pause::Watchpoint { address: 1, access: WatchKind::Write, .. })));
+    assert!(matches!(vm.step(), Status::Paused(Pause::Watchpoint { address: 1, access: WatchKind::Read, .. })));
+    assert!(matches!(vm.step(), Status::Paused(Pause::Watchpoint { address: 0, access: WatchKind::Read, .. })));
+    assert!(matches!(vm.step(), Status::Halted));
+
+    // Heap writes and host accesses
+    let mut vm = MikuVM::from_program(vec![Box::new(Push::new(MikuType::U64(3))), Box::new(Alloc::new())]);
+    let heap = vm.add_watchpoint(HEAP_START + 2..HEAP_START + 3, WatchKind::Write);
+    vm.write_ptr(MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_START as u64 + 2)), MikuType::U8(0)).unwrap();
+    assert!(vm.step().is_running());
+    assert!(matches!(vm.step(), Status::Paused(Pause::Watchpoint { address, .. }) if address == HEAP_START + 2));
+    assert!(vm.remove_watchpoint(heap).is_some());
+    assert_eq!(0, vm.debugger().watchpoints().count());
+
+    // Cells cleared by the garbage collector fire write watchpoints.
+    let mut vm = MikuVM::with_config(VmConfig::builder().gc_threshold(2).build().unwrap());
+    vm.load_program(vec![
+        Box::new(Push::new(MikuType::U64(2))),
+        Box::new(Alloc::new()),
+        Box::new(Pop::new()),
+        Box::new(Push::new(MikuType::U64(1))),
+        Box::new(Alloc::new()),
+    ]);
+    vm.add_watchpoint(HEAP_START + 1..HEAP_START + 2, WatchKind::Write);
+    assert!(matches!(vm.run(), Status::Paused(Pause::Watchpoint { address, .. }) if address == HEAP_START + 1));
+    assert_eq!(2, vm.pc());
+    assert_eq!(0, vm.gc_stats().collections);
+    // The second allocation only covers the first cell, the collector clears the second one.
+    assert!(matches!(vm.run(), Status::Paused(Pause::Watchpoint { address, .. }) if address == HEAP_START + 1));
+    assert_eq!(5, vm.pc());
+    assert_eq!(1, vm.gc_stats().collections);
+
+    // run_program doesn't stop at breakpoints.
+    let mut vm = MikuVM::from_program(program());
+    vm.add_breakpoint(1);
+    assert!(vm.run_program().is_ok());
+    assert_eq!(5, vm.pc());
+}
+
+#[derive(Debug)]
+struct Tracer(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+impl Tracer {
+    fn log(&self, event: String) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+impl Observer for Tracer {
+    fn before_inst(&mut self, pc: usize, inst: &dyn Inst) {
+        self.log(format!("before {} {:?}", pc, inst.encode()));
+    }
+
+    fn after_inst(&mut self, pc: usize, _inst: &dyn Inst) {
+        self.log(format!("after {}", pc));
+    }
+
+    fn on_memory_write(&mut self, address: usize, old: MikuType, new: MikuType) {
+        self.log(format!("write {} {} -> {}", address, old, new));
+    }
+
+    fn on_push(&mut self, value: MikuType) {
+        self.log(format!("push {}", value));
+    }
+
+    fn on_pop(&mut self, value: MikuType) {
+        self.log(format!("pop {}", value));
+    }
+
+    fn on_error(&mut self, pc: usize, error: &MikuError) {
+        self.log(format!("error {} {}", pc, error));
+    }
+}
+
+#[test]
+fn observer_test() {
+    let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let mut vm = MikuVM::from_program(vec![
+        Box::new(Def::new(MikuType::U8(9), DATA_START)),
+        Box::new(Push::new(MikuType::U8(1))),
+        Box::new(Pop::new()),
+        Box::new(Pop::new()),
+    ]);
+    vm.add_observer(Box::new(Tracer(events.clone())));
+    assert!(vm.run_program().is_err());
+    assert_eq!(
+        vec![
+            format!("before 0 {:?}", Def::new(MikuType::U8(9), DATA_START).encode()),
+            format!("write {} null -> u8 9", DATA_START),
+            "after 0".to_string(),
+            "before 1 [0, 0, 1]".to_string(),
+            "push u8 1".to_string(),
+            "after 1".to_string(),
+            "before 2 [1]".to_string(),
+            "pop u8 1".to_string(),
+            "after 2".to_string(),
+            "before 3 [1]".to_string(),
+            "error 3 STACK UNDERFLOW".to_string(),
+        ],
+        *events.lock().unwrap()
+    );
+
+    // Heap writes report the old values.
+    events.lock().unwrap().clear();
+    let heap = MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_START as u64));
+    vm.write_ptr(heap, MikuType::I8(-1)).unwrap();
+    let block = vm.alloc(1).unwrap();
+    vm.free(block).unwrap();
+    assert_eq!(
+        vec![
+            format!("write {} null -> i8 -1", HEAP_START),
+            format!("write {} i8 -1 -> null", HEAP_START),
+            format!("write {} null -> null", HEAP_START),
+        ],
+        *events.lock().unwrap()
+    );
+
+    // Cells cleared by the garbage collector are reported like freed ones.
+    events.lock().unwrap().clear();
+    let block = vm.alloc(1).unwrap();
+    vm.write_ptr(block, MikuType::I8(5)).unwrap();
+    assert_eq!(1, vm.collect_garbage());
+    assert_eq!(
+        vec![
+            format!("write {} null -> null", HEAP_START),
+            format!("write {} null -> i8 5", HEAP_START),
+            format!("write {} i8 5 -> null", HEAP_START),
+        ],
+        *events.lock().unwrap()
+    );
+
+    // Observers can be removed.
+    assert_eq!(1, vm.take_observers().len());
+    vm.stack_push(MikuType::U8(0)).unwrap();
+    assert_eq!(3, events.lock().unwrap().len());
+}
+
+#[test]
+fn json_test() {
+    let mut vm = MikuVM::from_program(vec![
+        Box::new(Def::new(MikuType::U128(u128::MAX), DATA_START + 2)),
+        Box::new(Push::new(MikuType::U64(2))),
+        Box::new(Alloc::new()),
+        Box::new(Push::new(MikuType::I8(-1))),
+        Box::new(Pop::new()),
+    ]);
+    vm.run_for(4);
+    let state = vm.to_json();
+
+    let registers = state.get("registers").unwrap();
+    assert_eq!(Some(&Json::Number(4)), registers.get("pc"));
+    assert_eq!(Some(&Json::Number(2)), registers.get("stack_top"));
+    assert_eq!(Some(&Json::Null), registers.get("gas"));
+    assert_eq!(
+        r#"{"memory_size":1024,"stack":[0,307],"data":[307,614],"heap":[614,1024]}"#,
+        state.get("layout").unwrap().to_string()
+    );
+    assert_eq!(
+        format!(r#"[{{"pc":4,"base":0,"top":2,"entries":["ptr heap {}","i8 -1"]}}]"#, HEAP_START),
+        state.get("stack_frames").unwrap().to_string()
+    );
+    assert_eq!(
+        format!(r#"[{{"address":{},"value":"u128 {}"}}]"#, DATA_START + 2, u128::MAX),
+        state.get("data").unwrap().to_string()
+    );
+    assert_eq!(
+        format!(r#"[{{"start":{},"size":2,"cells":["null","null"]}}]"#, HEAP_START),
+        state.get("heap_blocks").unwrap().to_string()
+    );
+    let program = state.get("program").unwrap().as_array().unwrap();
+    assert_eq!(5, program.len());
+    assert_eq!(r#"{"pc":3,"opcode":0,"inst":"push i8 -1"}"#, program[3].to_string());
+
+    // Strings are escaped.
+    assert_eq!(r#"["a\"b\\c\nd\u0001"]"#, Json::from_iter(["a\"b\\c\nd\u{1}"]).to_string());
+
+    // The display output lists the program and the used cells.
+    let display = vm.to_string();
+    assert!(display.contains(">    4: pop"));
+    assert!(display.contains(&format!("{:>4}: u128 {}", DATA_START + 2, u128::MAX)));
+    assert!(display.contains(&format!("block {}..{}", HEAP_START, HEAP_START + 2)));
+}
+
+#[test]
+fn runtime_error_test() {
+    // Errors record the failed instruction and the stack at the time of the failure.
+    let mut vm = MikuVM::from_program(vec![
+        Box::new(Push::new(MikuType::U8(1))),
+        Box::new(Plus::new()),
+    ]);
+    let error = vm.run_program().unwrap_err();
+    assert!(matches!(error.error(), MikuError::StackUnderflow));
+    assert_eq!(1, error.pc());
+    assert_eq!("plus", error.inst());
+    assert_eq!(None, error.address());
+    assert_eq!(&[StackFrame { pc: 1, base: STACK_START, top: STACK_START }], error.backtrace());
+    assert_eq!("STACK UNDERFLOW AT PC 1 (plus)", error.to_string());
+
+    // Memory faults record the address.
+    let ptr = MikuType::Ptr(Pointer::new(Segment::Heap, HEAP_END as u64));
+    let mut vm = MikuVM::from_program(vec![
+        Box::new(Push::new(ptr)),
+        Box::new(Free::new()),
+    ]);
+    let error = vm.run_program().unwrap_err();
+    assert!(matches!(error.error(), MikuError::InvalidFree(_)));
+    assert_eq!(Some(HEAP_END), error.address());
+    assert_eq!(format!("INVALID FREE: {0} AT PC 1 (free) ADDRESS {0}", HEAP_END), error.to_string());
+
+    let mut vm = MikuVM::from_program(vec![
+        Box::new(Def::new(MikuType::U8(1), DATA_START + 1)),
+        Box::new(Def::new(MikuType::U8(2), DATA_START + 1)),
+    ]);
+    let error = vm.run_program().unwrap_err();
+    assert!(matches!(error.error(), MikuError::UsedDataSpace));
+    assert_eq!(Some(DATA_START + 1), error.address());
+
+    // Faults of the host between steps aren't reported.
+    let mut vm = MikuVM::from_program(vec![Box::new(Pop::new())]);
+    assert!(vm.define_data(MikuType::U8(1), 0).is_err());
+    match vm.step() {
+        Status::Trapped(error) => assert_eq!(None, error.address()),
+        status => panic!("{:?}", status),
+    }
+}
+
+#[test]
+fn trap_test() {
+    // Handlers see the state from before the failed instruction and can resume it.
+    let mut vm = MikuVM::from_program(vec![
+        Box::new(Push::new(MikuType::U8(1))),
+        Box::new(Plus::new()),
+        Box::new(Push::new(MikuType::U8(3))),
+    ]);
+    vm.set_trap_handler(TrapKind::StackUnderflow, Box::new(TrapFn::new(|vm, error| {
+        assert_eq!(1, error.pc());
+        assert_eq!(1, vm.pc());
+        assert_eq!(1, vm.stack_top());
+        vm.stack_push(MikuType::U8(2)).unwrap();
+        TrapAction::Resume
+    })));
+    assert!(vm.run_program().is_ok());
+    assert_eq!(vec![MikuType::U8(3), MikuType::U8(3)], vm.stack()[0..2].to_vec());
+
+    // Running out of gas can be resumed after topping up.
+    let mut vm = MikuVM::from_program(vec![
+        Box::new(Push::new(MikuType::U8(1))),
+        Box::new(Push::new(MikuType::U8(2))),
+    ]);
+    vm.set_gas(Some(1));
+    vm.set_trap_handler(TrapKind::OutOfGas, Box::new(TrapFn::new(|vm, _| {
+        vm.add_gas(1);
+        TrapAction::Resume
+    })));
+    assert!(vm.run_program().is_ok());
+    assert_eq!(Some(0), vm.gas());
+
+    // Skipping continues with the next instruction.
+    let mut vm = MikuVM::from_program(vec![
+        Box::new(Push::new(MikuType::U8(1))),
+        Box::new(Free::new()),
+        Box::new(Push::new(MikuType::U8(2))),
+    ]);
+    vm.set_trap_handler(TrapKind::Other, Box::new(TrapFn::new(|_, _| TrapAction::Skip)));
+    assert!(matches!(vm.step(), Status::Running));
+    assert!(matches!(vm.step(), Status::Running));
+    assert_eq!(2, vm.pc());
+    assert!(matches!(vm.step(), Status::Halted));
+    assert_eq!(vec![MikuType::U8(1), MikuType::U8(2)], vm.stack()[0..2].to_vec());
+
+    // Aborting stops the program with the error.
+    let mut vm = MikuVM::from_program(vec![
+        Box::new(Push::new(MikuType::I8(1))),
+        Box::new(Push::new(MikuType::I8(0))),
+        Box::new(Div::new()),
+    ]);
+    vm.set_trap_handler(TrapKind::DivisionByZero, Box::new(TrapFn::new(|_, _| TrapAction::Abort)));
+    let error = vm.run_program().unwrap_err();
+    assert!(matches!(error.error(), MikuError::DivisionByZeroError));
+    assert_eq!(2, vm.pc());
+    assert_eq!(2, vm.stack_top());
+
+    // Handlers only handle their kind of error and can be removed.
+    let mut vm = MikuVM::from_program(vec![Box::new(Pop::new())]);
+    vm.set_trap_handler(TrapKind::StackOverflow, Box::new(TrapFn::new(|_, _| TrapAction::Skip)));
+    assert!(vm.run_program().is_err());
+    assert!(vm.remove_trap_handler(TrapKind::StackOverflow).is_some());
+    assert!(vm.remove_trap_handler(TrapKind::StackOverflow).is_none());
+    assert_eq!(TrapKind::SegmentationFault, TrapKind::from(&MikuError::SegmentationFault));
+    assert_eq!(TrapKind::UnknownOpcode, TrapKind::from(&MikuError::UnknownExtendedOpcode(1)));
+}
+
+#[test]
+fn interrupt_test() {
+    // A supervisor thread stops a vm that never terminates.
+    let mut vm = MikuVM::from_program(vec![
+        Box::new(Push::new(MikuType::U8(1))),
+        Box::new(Pop::new()),
+        Box::new(Pop::new()),
+    ]);
+    vm.set_trap_handler(TrapKind::StackUnderflow, Box::new(TrapFn::new(|_, _| TrapAction::Resume)));
+    let handle = vm.interrupt_handle();
+    let supervisor = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        handle.interrupt();
+    });
+    let error = vm.run_program().unwrap_err();
+    supervisor.join().unwrap();
+    assert!(matches!(error.error(), MikuError::Interrupted));
+    assert_eq!("INTERRUPTED AT PC 2 (pop)", error.to_string());
+    assert_eq!(2, vm.pc());
+    assert_eq!(STACK_START, vm.stack_top());
+
+    // The interrupt is consumed and the vm can continue.
+    let handle = vm.interrupt_handle();
+    assert!(!handle.is_interrupted());
+    vm.remove_trap_handler(TrapKind::StackUnderflow);
+    assert!(matches!(vm.run_program().unwrap_err().error(), MikuError::StackUnderflow));
+
+    // Withdrawn interrupts don't stop the vm.
+    let mut vm = MikuVM::from_program(vec![Box::new(Push::new(MikuType::U8(1)))]);
+    let handle = vm.interrupt_handle();
+    handle.interrupt();
+    assert!(handle.is_interrupted());
+    handle.clear();
+    assert!(vm.run_program().is_ok());
+
+    // A halted vm isn't interrupted and the interrupt doesn't reach the next program.
+    handle.interrupt();
+    assert!(matches!(vm.step(), Status::Halted));
+    assert!(!handle.is_interrupted());
+    vm.load_program(vec![Box::new(Push::new(MikuType::U8(2)))]);
+    assert!(vm.run_program().is_ok());
+
+    // Loading a program withdraws pending interrupts.
+    handle.interrupt();
+    vm.load_program(vec![Box::new(Push::new(MikuType::U8(3)))]);
+    assert!(vm.run_program().is_ok());
+    assert_eq!(3, vm.stack_top());
 }
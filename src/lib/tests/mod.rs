@@ -1,3 +1,6 @@
 pub mod miku_type_tests;
 pub mod miku_inst_tests;
 pub mod miku_vm_tests;
+pub mod miku_bigint_tests;
+pub mod miku_program_tests;
+pub mod miku_snapshot_tests;
@@ -0,0 +1,128 @@
+//! Trap handlers.
+//!
+//! By default an instruction that fails stops the program. A [`TrapHandler`] registered on a
+//! [`crate::miku::MikuVM`] for the [`TrapKind`] of the error is called instead and decides how
+//! execution continues with a [`TrapAction`]. Before the handler is called the program counter
+//! and the top of the stack are rewound to where they were before the failed instruction, so
+//! the handler sees the operands of the instruction on the stack and can fix up the state.
+//!
+//! ## Examples
+//! ``` rust
+//! use vm::{inst::*, miku::MikuVM, trap::{TrapAction, TrapFn, TrapKind}, types::MikuType};
+//!
+//! // Division by zero results in zero.
+//! let mut vm = MikuVM::from_program(vec![
+//!     Box::new(Push::new(MikuType::I32(7))),
+//!     Box::new(Push::new(MikuType::I32(0))),
+//!     Box::new(Div::new()),
+//! ]);
+//! vm.set_trap_handler(TrapKind::DivisionByZero, Box::new(TrapFn::new(|vm, _| {
+//!     vm.stack_pop().unwrap();
+//!     vm.stack_pop().unwrap();
+//!     vm.stack_push(MikuType::I32(0)).unwrap();
+//!     TrapAction::Skip
+//! })));
+//! vm.run_program().unwrap();
+//! assert_eq!(MikuType::I32(0), vm.stack()[0]);
+//! ```
+
+use std::fmt::Debug;
+
+use crate::{error::{MikuError, RuntimeError}, miku::MikuVM};
+
+/// The categories of errors trap handlers are registered for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrapKind {
+    /// [`MikuError::StackOverflow`]
+    StackOverflow,
+    /// [`MikuError::StackUnderflow`]
+    StackUnderflow,
+    /// [`MikuError::SegmentationFault`]
+    SegmentationFault,
+    /// [`MikuError::ProtectionFault`]
+    ProtectionFault,
+    /// [`MikuError::DivisionByZeroError`]
+    DivisionByZero,
+    /// [`MikuError::UnknownOpcode`] and [`MikuError::UnknownExtendedOpcode`] returned by the
+    /// [`crate::program::UnknownInst`]s of a program decoded with
+    /// [`crate::program::decode_program_lenient`].
+    UnknownOpcode,
+    /// [`MikuError::OutOfMemory`]
+    OutOfMemory,
+    /// [`MikuError::OutOfGas`]
+    OutOfGas,
+    /// Every other error.
+    Other,
+}
+
+impl From<&MikuError> for TrapKind {
+    fn from(value: &MikuError) -> Self {
+        match value {
+            MikuError::StackOverflow => TrapKind::StackOverflow,
+            MikuError::StackUnderflow => TrapKind::StackUnderflow,
+            MikuError::SegmentationFault => TrapKind::SegmentationFault,
+            MikuError::ProtectionFault(_) => TrapKind::ProtectionFault,
+            MikuError::DivisionByZeroError => TrapKind::DivisionByZero,
+            MikuError::UnknownOpcode(_) | MikuError::UnknownExtendedOpcode(_) => TrapKind::UnknownOpcode,
+            MikuError::OutOfMemory(_) => TrapKind::OutOfMemory,
+            MikuError::OutOfGas(_) => TrapKind::OutOfGas,
+            _ => TrapKind::Other,
+        }
+    }
+}
+
+/// How execution continues after a trap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrapAction {
+    /// Executes the failed instruction again. The handler has to fix the cause of the error,
+    /// otherwise the instruction traps again.
+    Resume,
+    /// Continues with the instruction after the failed one.
+    Skip,
+    /// Stops the program with the error.
+    Abort,
+}
+
+/// # The trap handler trait.
+///
+/// The bound on [`Send`] keeps vms with registered handlers movable between threads.
+pub trait TrapHandler: Debug + Send {
+    /// Called when an instruction fails with an error of the kind the handler is registered for.
+    /// The error holds the program counter and the disassembly of the failed instruction.
+    fn handle(&mut self, vm: &mut MikuVM, error: &RuntimeError) -> TrapAction;
+}
+
+/// A [`TrapHandler`] made of a callback.
+pub struct TrapFn<F>
+where
+    F: FnMut(&mut MikuVM, &RuntimeError) -> TrapAction,
+{
+    handle: F,
+}
+
+impl<F> TrapFn<F>
+where
+    F: FnMut(&mut MikuVM, &RuntimeError) -> TrapAction,
+{
+    pub fn new(handle: F) -> Self {
+        Self { handle }
+    }
+}
+
+impl<F> Debug for TrapFn<F>
+where
+    F: FnMut(&mut MikuVM, &RuntimeError) -> TrapAction,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrapFn").finish_non_exhaustive()
+    }
+}
+
+impl<F> TrapHandler for TrapFn<F>
+where
+    F: FnMut(&mut MikuVM, &RuntimeError) -> TrapAction + Send,
+{
+    fn handle(&mut self, vm: &mut MikuVM, error: &RuntimeError) -> TrapAction {
+        (self.handle)(vm, error)
+    }
+}
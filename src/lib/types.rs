@@ -1,68 +1,194 @@
 //! Builtin types.
 
-use crate::{error::MikuError, tools};
-use std::ops::{Add, Sub, Mul, Div};
+use crate::{error::{MikuError, ParseTypeError}, tools};
+use std::{cmp::Ordering, fmt::Display, ops::{Add, Sub, Mul, Div}, str::FromStr};
 
 /// Each variant encapsulates a builtin type.
-/// Currently only supports numeric types.
-/// The U64 is also used as a pointer.
+/// Currently supports numeric types and pointers.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MikuType {
     U8(u8),
     U16(u16),
     U32(u32),
-    /// Also used as a pointer.
     U64(u64),
+    U128(u128),
     I8(i8),
     I16(i16),
     I32(i32),
     I64(i64),
+    I128(i128),
     F32(f32),
     F64(f64),
     NULL,
+    /// A pointer tagged with the segment it points into.
+    Ptr(Pointer),
+}
+
+/// The memory segments of [`crate::miku::MikuVM`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Segment {
+    Stack,
+    Data,
+    Heap,
+    /// The byte-addressed memory. Pointers into it hold byte offsets instead of cell addresses.
+    Bytes,
+}
+
+impl TryFrom<u8> for Segment {
+    type Error = MikuError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(Segment::Stack),
+            0x01 => Ok(Segment::Data),
+            0x02 => Ok(Segment::Heap),
+            0x03 => Ok(Segment::Bytes),
+            _ => Err(MikuError::BytesConversionError),
+        }
+    }
+}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Segment::Stack => write!(f, "stack"),
+            Segment::Data => write!(f, "data"),
+            Segment::Heap => write!(f, "heap"),
+            Segment::Bytes => write!(f, "bytes"),
+        }
+    }
+}
+
+impl FromStr for Segment {
+    type Err = ParseTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stack" => Ok(Segment::Stack),
+            "data" => Ok(Segment::Data),
+            "heap" => Ok(Segment::Heap),
+            "bytes" => Ok(Segment::Bytes),
+            _ => Err(ParseTypeError::UnknownSegment(s.to_string())),
+        }
+    }
+}
+
+/// A pointer to a memory address.
+/// The pointer remembers which segment it points into so the vm can check that
+/// it never leaves that segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pointer {
+    segment: Segment,
+    address: u64,
+}
+
+impl Pointer {
+    pub fn new(segment: Segment, address: u64) -> Self {
+        Self { segment, address }
+    }
+
+    /// The segment the pointer points into.
+    pub fn segment(&self) -> Segment {
+        self.segment
+    }
+
+    /// The address the pointer points to.
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Encodes the pointer as the segment byte followed by the address in little endian.
+    pub fn to_le_bytes(self) -> [u8; 9] {
+        let mut bytes = [0; 9];
+        bytes[0] = self.segment as u8;
+        bytes[1..].copy_from_slice(&self.address.to_le_bytes());
+        bytes
+    }
+
+    /// Decodes a pointer encoded by [`Pointer::to_le_bytes`].
+    /// # Returns
+    /// - `Ok(Pointer)`
+    /// - [`MikuError::BytesConversionError`] if the segment byte is not recognized.
+    pub fn from_le_bytes(bytes: [u8; 9]) -> Result<Self, MikuError> {
+        let segment = Segment::try_from(bytes[0])?;
+        let address = u64::from_le_bytes(tools::convert_bytes(&bytes[1..])?);
+        Ok(Self::new(segment, address))
+    }
+}
+
+/// The access permissions of a memory segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Access {
+    /// The segment can be read and written.
+    #[default]
+    ReadWrite,
+    /// The segment can only be read. Writes result in [`MikuError::ProtectionFault`].
+    ReadOnly,
+}
+
+/// The policy used to reconcile the operand types of arithmetic operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Promotion {
+    /// Both operands have to be of the same type.
+    #[default]
+    Strict,
+    /// C-like widening. Mismatched operands are converted to a common type:
+    /// - a float if either operand is a float ([`MikuType::F64`] if either is one),
+    /// - the wider type if both integers have the same signedness,
+    /// - a signed type wide enough for both operands if they don't. [`MikuType::U128`] values are
+    ///   promoted to [`MikuType::I128`] and fail with [`MikuError::ArithmeticOverflow`] if they
+    ///   don't fit.
+    Widening,
 }
 
 /// Used to implement the arithmetic traits: [Add], [Sub], [Mul]
 /// for [MikuType].
 macro_rules! impl_arith_trait {
-    ($operation: ident, $method: ident) => {
+    ($operation: ident, $method: ident, $checked: ident) => {
         /// [`$operation`] implementation for [`MikuType`].
         /// ### Results in
         /// - [`MikuType`]
         /// - [`MikuError::UndefinedOperationBetweenTypesError`] is returned if the types of the two parameters
-        /// don't match.
+        ///   don't match.
+        /// - [`MikuError::ArithmeticOverflow`] is returned if the result of an integer operation
+        ///   doesn't fit into the type.
 
         impl $operation for MikuType {
             type Output = Result<MikuType, MikuError>;
             
             fn $method(self, rhs: Self) -> Self::Output {
-                impl_arith_operations!(self, $method, rhs)
+                impl_arith_operations!(self, $method, $checked, rhs)
             }
         }
     }
 }
 
 /// Used for implementing the arithmetic operations for [MikuType].
+/// Integers use the checked operation and fail with [`MikuError::ArithmeticOverflow`], floats
+/// use the regular one.
 macro_rules! impl_arith_operations {
-    ($self: ident, $method: ident, $rhs: ident) => {
+    ($self: ident, $method: ident, $checked: ident, $rhs: ident) => {{
+        let overflow = || MikuError::ArithmeticOverflow(format!("{}({:?}, {:?})", stringify!($method), $self, $rhs));
         match ($self, $rhs) {
-            (MikuType::U8(a), MikuType::U8(b))   => Ok(MikuType::U8(a.$method(b))),
-            (MikuType::U16(a), MikuType::U16(b)) => Ok(MikuType::U16(a.$method(b))),
-            (MikuType::U32(a), MikuType::U32(b)) => Ok(MikuType::U32(a.$method(b))),
-            (MikuType::U64(a), MikuType::U64(b)) => Ok(MikuType::U64(a.$method(b))),
-            (MikuType::I8(a), MikuType::I8(b))   => Ok(MikuType::I8(a.$method(b))),
-            (MikuType::I16(a), MikuType::I16(b)) => Ok(MikuType::I16(a.$method(b))),
-            (MikuType::I32(a), MikuType::I32(b)) => Ok(MikuType::I32(a.$method(b))),
-            (MikuType::I64(a), MikuType::I64(b)) => Ok(MikuType::I64(a.$method(b))),
+            (MikuType::U8(a), MikuType::U8(b))   => a.$checked(b).map(MikuType::U8).ok_or_else(overflow),
+            (MikuType::U16(a), MikuType::U16(b)) => a.$checked(b).map(MikuType::U16).ok_or_else(overflow),
+            (MikuType::U32(a), MikuType::U32(b)) => a.$checked(b).map(MikuType::U32).ok_or_else(overflow),
+            (MikuType::U64(a), MikuType::U64(b)) => a.$checked(b).map(MikuType::U64).ok_or_else(overflow),
+            (MikuType::I8(a), MikuType::I8(b))   => a.$checked(b).map(MikuType::I8).ok_or_else(overflow),
+            (MikuType::I16(a), MikuType::I16(b)) => a.$checked(b).map(MikuType::I16).ok_or_else(overflow),
+            (MikuType::I32(a), MikuType::I32(b)) => a.$checked(b).map(MikuType::I32).ok_or_else(overflow),
+            (MikuType::I64(a), MikuType::I64(b)) => a.$checked(b).map(MikuType::I64).ok_or_else(overflow),
+            (MikuType::U128(a), MikuType::U128(b)) => a.$checked(b).map(MikuType::U128).ok_or_else(overflow),
+            (MikuType::I128(a), MikuType::I128(b)) => a.$checked(b).map(MikuType::I128).ok_or_else(overflow),
             (MikuType::F32(a), MikuType::F32(b)) => Ok(MikuType::F32(a.$method(b))),
             (MikuType::F64(a), MikuType::F64(b)) => Ok(MikuType::F64(a.$method(b))),
-            _ => Err(MikuError::UndefinedOperationBetweenTypesError(format!("{}({:?}, {:?})", stringify!(method), $self, $rhs))),
+            _ => Err(MikuError::UndefinedOperationBetweenTypesError(format!("{}({:?}, {:?})", stringify!($method), $self, $rhs))),
         }
-    }
+    }}
 }
 
 /// Used for implementing `From<MikuType> for Vec<u8>` for [MikuType].
-/// Automatically handles the [`MikuType::NULL`] variant.
+/// Automatically handles the [`MikuType::NULL`] and [`MikuType::Ptr`] variants.
 macro_rules! match_to_bytes {
     ($self: expr, { $ ( $variant: ident => $tag: expr), * }) => {{
         let mut bytes = Vec::new();
@@ -77,6 +203,10 @@ macro_rules! match_to_bytes {
             MikuType::NULL => {
                 bytes.push(0x0A);
             }
+            MikuType::Ptr(pointer) => {
+                bytes.push(0x0B);
+                bytes.extend(pointer.to_le_bytes());
+            }
         }
         
         bytes
@@ -84,7 +214,8 @@ macro_rules! match_to_bytes {
 }
 
 /// Used for implementing `TryFrom[&u8] for MikuType` for [MikuType].
-/// Automatically handles the `0x0A` case which is [`MikuType::NULL`].
+/// Automatically handles the `0x0A` case which is [`MikuType::NULL`] and the `0x0B` case which
+/// is [`MikuType::Ptr`].
 macro_rules! match_from_bytes {
     ($type_identifier_byte: expr, $le_bytes: expr, { $ ($tag: expr => $variant: ident | $type: ident), * }) => {
         match $type_identifier_byte {
@@ -92,6 +223,7 @@ macro_rules! match_from_bytes {
                 $tag => Ok(Self::$variant($type::from_le_bytes(tools::convert_bytes($le_bytes)?))),
             )*
             0x0A => Ok(Self::NULL),
+            0x0B => Ok(Self::Ptr(Pointer::from_le_bytes(tools::convert_bytes($le_bytes)?)?)),
             _ => Err(MikuError::UnknownTypeError($type_identifier_byte)),
         }
     };
@@ -106,7 +238,7 @@ impl From<MikuType> for Vec<u8> {
 		match_to_bytes!(
             value, { U8 => 0x00, U16 => 0x01, U32 => 0x02, U64 => 0x03,
                      I8 => 0x04, I16 => 0x05, I32 => 0x06, I64 => 0x07,
-                     F32 => 0x08, F64 => 0x09 }
+                     F32 => 0x08, F64 => 0x09, U128 => 0x0C, I128 => 0x0D }
         )
 	}
 }
@@ -127,37 +259,264 @@ impl TryFrom<&[u8]> for MikuType {
             type_identifier_byte, le_bytes, 
             { 0x00 => U8 | u8, 0x01 => U16 | u16, 0x02 => U32 | u32, 0x03 => U64 | u64,
               0x04 => I8 | i8, 0x05 => I16 | i16, 0x06 => I32 | i32, 0x07 => I64 | i64,
-              0x08 => F32 | f32, 0x09 => F64 | f64
+              0x08 => F32 | f32, 0x09 => F64 | f64, 0x0C => U128 | u128, 0x0D => I128 | i128
         })
 	}
 }
 
-impl_arith_trait!(Add, add);
-impl_arith_trait!(Sub, sub);
-impl_arith_trait!(Mul, mul);
+impl_arith_trait!(Add, add, checked_add);
+impl_arith_trait!(Sub, sub, checked_sub);
+impl_arith_trait!(Mul, mul, checked_mul);
 
 /// [Div] implementation for [MikuType].
 /// #### Results in
 /// - [`MikuType`]
 /// - [`MikuError::UndefinedOperationBetweenTypesError`] is returned if the types of the two parameters
-/// don't match.
+///   don't match.
 /// - [`MikuError::DivisionByZeroError`] is returned if the second parameter is 0.
+/// - [`MikuError::ArithmeticOverflow`] is returned if the minimum of a signed type is divided
+///   by -1.
 impl Div for MikuType {
     type Output = Result<MikuType, MikuError>;
 
     fn div(self, rhs: Self) -> Self::Output {
-        if rhs == MikuType::U8(0) || rhs == MikuType::U16(0) || rhs == MikuType::U32(0) || rhs == MikuType::U64(0) ||
-            rhs == MikuType::I8(0) || rhs == MikuType::I16(0) || rhs == MikuType::I32(0) || rhs == MikuType::I64(0) ||
-            rhs == MikuType::F32(0.0) || rhs == MikuType::F64(0.0)
-        {
+        if rhs.is_zero() {
             return Err(MikuError::DivisionByZeroError);
         }
 
-        impl_arith_operations!(self, div, rhs)
+        impl_arith_operations!(self, div, checked_div, rhs)
+    }
+}
+
+/// [PartialOrd] implementation for [MikuType].
+/// Values of the same variant are compared by value and pointers into the same segment are
+/// compared by address. Values of different variants are unordered, use
+/// [`MikuType::numeric_cmp`] to compare them numerically.
+impl PartialOrd for MikuType {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (MikuType::U8(a), MikuType::U8(b))   => a.partial_cmp(b),
+            (MikuType::U16(a), MikuType::U16(b)) => a.partial_cmp(b),
+            (MikuType::U32(a), MikuType::U32(b)) => a.partial_cmp(b),
+            (MikuType::U64(a), MikuType::U64(b)) => a.partial_cmp(b),
+            (MikuType::I8(a), MikuType::I8(b))   => a.partial_cmp(b),
+            (MikuType::I16(a), MikuType::I16(b)) => a.partial_cmp(b),
+            (MikuType::I32(a), MikuType::I32(b)) => a.partial_cmp(b),
+            (MikuType::I64(a), MikuType::I64(b)) => a.partial_cmp(b),
+            (MikuType::U128(a), MikuType::U128(b)) => a.partial_cmp(b),
+            (MikuType::I128(a), MikuType::I128(b)) => a.partial_cmp(b),
+            (MikuType::F32(a), MikuType::F32(b)) => a.partial_cmp(b),
+            (MikuType::F64(a), MikuType::F64(b)) => a.partial_cmp(b),
+            (MikuType::NULL, MikuType::NULL) => Some(Ordering::Equal),
+            (MikuType::Ptr(a), MikuType::Ptr(b)) if a.segment() == b.segment() => a.address().partial_cmp(&b.address()),
+            _ => None,
+        }
     }
 }
 
 impl MikuType {
+    /// Returns true if the value is a numeric zero.
+    pub fn is_zero(&self) -> bool {
+        match self {
+            MikuType::F32(value) => *value == 0.0,
+            MikuType::F64(value) => *value == 0.0,
+            _ => self.as_i128() == Some(0),
+        }
+    }
+
+    /// A total ordering over every [`MikuType`] value.
+    /// Values are ordered by their type identifier first and by their value second.
+    /// Floats are ordered with [`f64::total_cmp`] so `NaN` has a place in the order too.
+    pub fn total_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (MikuType::F32(a), MikuType::F32(b)) => a.total_cmp(b),
+            (MikuType::F64(a), MikuType::F64(b)) => a.total_cmp(b),
+            (MikuType::Ptr(a), MikuType::Ptr(b)) => (a.segment() as u8, a.address()).cmp(&(b.segment() as u8, b.address())),
+            _ => self.type_id().cmp(&other.type_id())
+                .then_with(|| self.partial_cmp(other).unwrap_or(Ordering::Equal)),
+        }
+    }
+
+    /// Compares two values numerically regardless of their variants, so `U8(3)` equals `I64(3)`.
+    /// Integers are compared exactly. If either side is a float both sides are compared as [`f64`].
+    /// Non numeric values fall back to [`PartialOrd`].
+    /// # Returns
+    /// - `Some(Ordering)` if the values are comparable.
+    /// - `None` if they aren't (e.g. a `NaN` or values of different non numeric types).
+    pub fn numeric_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (MikuType::U128(a), MikuType::U128(b)) => return Some(a.cmp(b)),
+            // Only a U128 can be larger than i128::MAX.
+            (MikuType::U128(a), _) if other.integer_kind().is_some() && *a > i128::MAX as u128 => return Some(Ordering::Greater),
+            (_, MikuType::U128(b)) if self.integer_kind().is_some() && *b > i128::MAX as u128 => return Some(Ordering::Less),
+            _ => {}
+        }
+
+        match (self.as_i128(), other.as_i128()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => match (self.as_f64(), other.as_f64()) {
+                (Some(a), Some(b)) => a.partial_cmp(&b),
+                _ => self.partial_cmp(other),
+            },
+        }
+    }
+
+    /// Numeric equality, see [`MikuType::numeric_cmp`].
+    pub fn numeric_eq(&self, other: &Self) -> bool {
+        self.numeric_cmp(other) == Some(Ordering::Equal)
+    }
+
+    /// The value as an [`i128`] if it is an integer that fits into one.
+    fn as_i128(&self) -> Option<i128> {
+        match *self {
+            MikuType::U128(value) => i128::try_from(value).ok(),
+            _ => self.integer_bits(),
+        }
+    }
+
+    /// The bits of an integer value as an [`i128`]. A [`MikuType::U128`] is reinterpreted
+    /// so casting the result keeps the semantics of Rust's `as` casts.
+    fn integer_bits(&self) -> Option<i128> {
+        match *self {
+            MikuType::U8(value) => Some(value as i128),
+            MikuType::U16(value) => Some(value as i128),
+            MikuType::U32(value) => Some(value as i128),
+            MikuType::U64(value) => Some(value as i128),
+            MikuType::U128(value) => Some(value as i128),
+            MikuType::I8(value) => Some(value as i128),
+            MikuType::I16(value) => Some(value as i128),
+            MikuType::I32(value) => Some(value as i128),
+            MikuType::I64(value) => Some(value as i128),
+            MikuType::I128(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The value as an [`f64`] if it is numeric.
+    fn as_f64(&self) -> Option<f64> {
+        match *self {
+            MikuType::F32(value) => Some(value as f64),
+            MikuType::F64(value) => Some(value),
+            MikuType::U128(value) => Some(value as f64),
+            _ => self.integer_bits().map(|value| value as f64),
+        }
+    }
+
+    /// Converts a numeric value to the type with the given type identifier.
+    /// The conversion follows the semantics of Rust's `as` casts.
+    /// # Returns
+    /// - `Ok(MikuType)` the converted value.
+    /// - [`MikuError::UnknownTypeError`] if the type identifier is not a numeric type.
+    /// - [`MikuError::UndefinedOperationBetweenTypesError`] if the value is not numeric.
+    pub fn cast(&self, type_id: u8) -> Result<MikuType, MikuError> {
+        let float = self.as_f64()
+            .ok_or_else(|| MikuError::UndefinedOperationBetweenTypesError(format!("cast({:?}, {})", self, type_id)))?;
+
+        if let Some(value) = self.integer_bits() {
+            return match type_id {
+                0x00 => Ok(MikuType::U8(value as u8)),
+                0x01 => Ok(MikuType::U16(value as u16)),
+                0x02 => Ok(MikuType::U32(value as u32)),
+                0x03 => Ok(MikuType::U64(value as u64)),
+                0x04 => Ok(MikuType::I8(value as i8)),
+                0x05 => Ok(MikuType::I16(value as i16)),
+                0x06 => Ok(MikuType::I32(value as i32)),
+                0x07 => Ok(MikuType::I64(value as i64)),
+                0x08 => Ok(MikuType::F32(float as f32)),
+                0x09 => Ok(MikuType::F64(float)),
+                0x0C => Ok(MikuType::U128(value as u128)),
+                0x0D => Ok(MikuType::I128(value)),
+                _ => Err(MikuError::UnknownTypeError(type_id)),
+            };
+        }
+
+        match type_id {
+            0x00 => Ok(MikuType::U8(float as u8)),
+            0x01 => Ok(MikuType::U16(float as u16)),
+            0x02 => Ok(MikuType::U32(float as u32)),
+            0x03 => Ok(MikuType::U64(float as u64)),
+            0x04 => Ok(MikuType::I8(float as i8)),
+            0x05 => Ok(MikuType::I16(float as i16)),
+            0x06 => Ok(MikuType::I32(float as i32)),
+            0x07 => Ok(MikuType::I64(float as i64)),
+            0x08 => Ok(MikuType::F32(float as f32)),
+            0x09 => Ok(MikuType::F64(float)),
+            0x0C => Ok(MikuType::U128(float as u128)),
+            0x0D => Ok(MikuType::I128(float as i128)),
+            _ => Err(MikuError::UnknownTypeError(type_id)),
+        }
+    }
+
+    /// Converts both operands of an arithmetic operation to a common type according to the
+    /// given [`Promotion`] policy. Operands that can't be promoted are returned unchanged and
+    /// the operation itself reports the mismatch.
+    /// # Returns
+    /// - `Ok((lhs, rhs))` the promoted operands.
+    /// - [`MikuError::ArithmeticOverflow`] if an unsigned operand doesn't fit into the signed
+    ///   common type.
+    pub fn promote(self, rhs: MikuType, policy: Promotion) -> Result<(MikuType, MikuType), MikuError> {
+        if policy == Promotion::Strict || self.type_id() == rhs.type_id() {
+            return Ok((self, rhs));
+        }
+
+        let target = match (self.integer_kind(), rhs.integer_kind()) {
+            (Some((lhs_signed, lhs_width)), Some((rhs_signed, rhs_width))) if lhs_signed == rhs_signed => {
+                Self::integer_type_id(lhs_signed, lhs_width.max(rhs_width))
+            }
+            (Some((lhs_signed, lhs_width)), Some((_, rhs_width))) => {
+                let (signed_width, unsigned_width, unsigned) = if lhs_signed { (lhs_width, rhs_width, rhs) } else { (rhs_width, lhs_width, self) };
+                if matches!(unsigned, MikuType::U128(value) if i128::try_from(value).is_err()) {
+                    return Err(MikuError::ArithmeticOverflow(format!("promote({:?}, {:?})", self, rhs)));
+                }
+                Self::integer_type_id(true, signed_width.max(unsigned_width * 2))
+            }
+            _ if self.as_f64().is_some() && rhs.as_f64().is_some() => {
+                if matches!(self, MikuType::F64(_)) || matches!(rhs, MikuType::F64(_)) { 0x09 } else { 0x08 }
+            }
+            _ => return Ok((self, rhs)),
+        };
+
+        Ok((self.cast(target)?, rhs.cast(target)?))
+    }
+
+    /// The signedness and width in bytes of an integer value.
+    fn integer_kind(&self) -> Option<(bool, usize)> {
+        match self {
+            MikuType::U8(_) => Some((false, 1)),
+            MikuType::U16(_) => Some((false, 2)),
+            MikuType::U32(_) => Some((false, 4)),
+            MikuType::U64(_) => Some((false, 8)),
+            MikuType::U128(_) => Some((false, 16)),
+            MikuType::I8(_) => Some((true, 1)),
+            MikuType::I16(_) => Some((true, 2)),
+            MikuType::I32(_) => Some((true, 4)),
+            MikuType::I64(_) => Some((true, 8)),
+            MikuType::I128(_) => Some((true, 16)),
+            _ => None,
+        }
+    }
+
+    /// The type identifier of the integer type with the given signedness and width in bytes.
+    fn integer_type_id(signed: bool, width: usize) -> u8 {
+        match (signed, width) {
+            (false, 1) => 0x00,
+            (false, 2) => 0x01,
+            (false, 4) => 0x02,
+            (false, 8) => 0x03,
+            (false, _) => 0x0C,
+            (true, 1) => 0x04,
+            (true, 2) => 0x05,
+            (true, 4) => 0x06,
+            (true, 8) => 0x07,
+            (true, _) => 0x0D,
+        }
+    }
+
+    /// The type identifier byte of the value. This is the first byte of the value's encoding.
+    pub fn type_id(&self) -> u8 {
+        Vec::from(*self)[0]
+    }
+
     /// Calculates the length of a specific MikuType when in byte form.
     /// # Returns
     /// - `Ok(size of the value)`
@@ -169,28 +528,129 @@ impl MikuType {
             0x02 | 0x06 | 0x08 => Ok(5),
             0x03 | 0x07 | 0x09 => Ok(9),
             0x0A => Ok(1),
+            0x0B => Ok(10),
+            0x0C | 0x0D => Ok(17),
+            _ => Err(MikuError::UnknownTypeError(type_identifier_byte)),
+        }
+    }
+
+    /// The name of the type with the given type identifier as used by [`MikuType::from_str`].
+    /// # Returns
+    /// - `Ok(name)`
+    /// - [`MikuError::UnknownTypeError`] if the type is not recognized.
+    pub fn type_name(type_identifier_byte: u8) -> Result<&'static str, MikuError> {
+        match type_identifier_byte {
+            0x00 => Ok("u8"),
+            0x01 => Ok("u16"),
+            0x02 => Ok("u32"),
+            0x03 => Ok("u64"),
+            0x04 => Ok("i8"),
+            0x05 => Ok("i16"),
+            0x06 => Ok("i32"),
+            0x07 => Ok("i64"),
+            0x08 => Ok("f32"),
+            0x09 => Ok("f64"),
+            0x0A => Ok("null"),
+            0x0B => Ok("ptr"),
+            0x0C => Ok("u128"),
+            0x0D => Ok("i128"),
             _ => Err(MikuError::UnknownTypeError(type_identifier_byte)),
         }
     }
+}
 
-    /// Takes a slice of string slices and turns them into a MikuType.
-    /// ! Temporary
-    /// ### Panics
-    pub fn from_strs(strs: &[&str]) -> MikuType {
-        if strs.len() != 2 {
-            panic!("EXPECTED: <type> <value>\nRECEIVED: {:?}", strs);
+/// Formats a [MikuType] as `<type> <value>`, e.g. `u8 69`, `f64 -1500.0`, `ptr heap 700` or `null`.
+/// The output can be parsed back with [`MikuType::from_str`].
+impl Display for MikuType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MikuType::U8(value) => write!(f, "u8 {}", value),
+            MikuType::U16(value) => write!(f, "u16 {}", value),
+            MikuType::U32(value) => write!(f, "u32 {}", value),
+            MikuType::U64(value) => write!(f, "u64 {}", value),
+            MikuType::I8(value) => write!(f, "i8 {}", value),
+            MikuType::I16(value) => write!(f, "i16 {}", value),
+            MikuType::I32(value) => write!(f, "i32 {}", value),
+            MikuType::I64(value) => write!(f, "i64 {}", value),
+            MikuType::U128(value) => write!(f, "u128 {}", value),
+            MikuType::I128(value) => write!(f, "i128 {}", value),
+            MikuType::F32(value) => write!(f, "f32 {:?}", value),
+            MikuType::F64(value) => write!(f, "f64 {:?}", value),
+            MikuType::NULL => write!(f, "null"),
+            MikuType::Ptr(pointer) => write!(f, "ptr {} {}", pointer.segment(), pointer.address()),
         }
+    }
+}
+
+/// Parses a [MikuType] from `<type> <value>`.
+///
+/// # Accepted formats
+/// - integers: `u8 69`, `i32 -420`, hexadecimal `u16 0xFFFF`, binary `i8 -0b101` and octal `u32 0o17`.
+/// - floats: `f32 1.5`, `f64 -1.5e3`, `f64 inf`, `f64 NaN`.
+/// - pointers: `ptr <stack|data|heap|bytes> <address>`.
+/// - `null`.
+///
+/// ### Results in
+/// - [`MikuType`]
+/// - [`ParseTypeError`] describing why the input couldn't be parsed.
+impl FromStr for MikuType {
+    type Err = ParseTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens: Vec<&str> = s.split_whitespace().collect();
 
-        match strs[0] {
-            "u8"  => MikuType::U8(strs[1].parse().expect("EXPECTED A NUMBER")),
-            "u16" => MikuType::U16(strs[1].parse().expect("EXPECTED A NUMBER")),
-            "u32" => MikuType::U32(strs[1].parse().expect("EXPECTED A NUMBER")),
-            "u64" => MikuType::U64(strs[1].parse().expect("EXPECTED A NUMBER")),
-            "i8"  => MikuType::I8(strs[1].parse().expect("EXPECTED A NUMBER")),
-            "i16" => MikuType::I16(strs[1].parse().expect("EXPECTED A NUMBER")),
-            "i32" => MikuType::I32(strs[1].parse().expect("EXPECTED A NUMBER")),
-            "i64" => MikuType::I64(strs[1].parse().expect("EXPECTED A NUMBER")),
-            _ => panic!("UNKNOWN TYPE: {}", strs[0]),
+        match tokens[..] {
+            ["null"] => Ok(MikuType::NULL),
+            ["ptr", segment, address] => Ok(MikuType::Ptr(Pointer::new(segment.parse()?, parse_integer("ptr", address)?))),
+            ["u8", value]  => Ok(MikuType::U8(parse_integer("u8", value)?)),
+            ["u16", value] => Ok(MikuType::U16(parse_integer("u16", value)?)),
+            ["u32", value] => Ok(MikuType::U32(parse_integer("u32", value)?)),
+            ["u64", value] => Ok(MikuType::U64(parse_integer("u64", value)?)),
+            ["i8", value]  => Ok(MikuType::I8(parse_integer("i8", value)?)),
+            ["i16", value] => Ok(MikuType::I16(parse_integer("i16", value)?)),
+            ["i32", value] => Ok(MikuType::I32(parse_integer("i32", value)?)),
+            ["i64", value] => Ok(MikuType::I64(parse_integer("i64", value)?)),
+            ["u128", value] => Ok(MikuType::U128(parse_integer("u128", value)?)),
+            ["i128", value] => Ok(MikuType::I128(parse_integer("i128", value)?)),
+            ["f32", value] => Ok(MikuType::F32(parse_float("f32", value)?)),
+            ["f64", value] => Ok(MikuType::F64(parse_float("f64", value)?)),
+            [type_name, _] | [type_name, _, _] if !matches!(type_name, "null" | "ptr") => {
+                Err(ParseTypeError::UnknownType(type_name.to_string()))
+            }
+            _ => Err(ParseTypeError::InvalidFormat(s.to_string())),
         }
     }
 }
+
+/// Parses an integer literal with an optional sign and an optional `0x`, `0b` or `0o` prefix.
+fn parse_integer<T: TryFrom<i128> + TryFrom<u128>>(type_name: &str, literal: &str) -> Result<T, ParseTypeError> {
+    let invalid = || ParseTypeError::InvalidLiteral(type_name.to_string(), literal.to_string());
+
+    let (negative, unsigned) = match literal.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, literal.strip_prefix('+').unwrap_or(literal)),
+    };
+    let (radix, digits) = match unsigned.get(..2) {
+        Some("0x") | Some("0X") => (16, &unsigned[2..]),
+        Some("0b") | Some("0B") => (2, &unsigned[2..]),
+        Some("0o") | Some("0O") => (8, &unsigned[2..]),
+        _ => (10, unsigned),
+    };
+
+    if digits.starts_with(['+', '-']) {
+        return Err(invalid());
+    }
+
+    let magnitude = u128::from_str_radix(digits, radix).map_err(|_| invalid())?;
+    if negative {
+        let value = 0i128.checked_sub_unsigned(magnitude).ok_or_else(invalid)?;
+        T::try_from(value).map_err(|_| invalid())
+    } else {
+        T::try_from(magnitude).map_err(|_| invalid())
+    }
+}
+
+/// Parses a float literal.
+fn parse_float<T: FromStr>(type_name: &str, literal: &str) -> Result<T, ParseTypeError> {
+    literal.parse().map_err(|_| ParseTypeError::InvalidLiteral(type_name.to_string(), literal.to_string()))
+}
@@ -3,31 +3,72 @@
 //! 
 //! ## Instructions
 //! * The instructions are impemented in the [`inst`] module.
+//! * Whole programs are encoded and decoded by the [`program`] module. A decoded
+//!   [`program::Program`] can be shared by vms running on different threads.
+//! * Custom instructions are registered in a [`program::InstRegistry`] and use the extended opcode.
 //! 
 //! | name | opcode | operand 1 | operand 2 | operand 3 |
 //! | ---- | ------ | --------- | --------- | --------- |
 //! | push | 0      | [`types::MikuType`] | - | - |
 //! | pop  | 1      | - | - | - |
 //! | def  | 2      | [`types::MikuType`] | address | - |
+//! | defstruct | 3 | id | [`layout::StructLayout`] | - |
+//! | fget | 4      | struct id | field index | - |
+//! | fset | 5      | struct id | field index | - |
+//! | plus | 6      | - | - | - |
+//! | minus | 7     | - | - | - |
+//! | mult | 8      | - | - | - |
+//! | div  | 9      | - | - | - |
+//! | load | 10     | type identifier | - | - |
+//! | store | 11    | - | - | - |
+//! | alloc | 12    | - | - | - |
+//! | free | 13     | - | - | - |
+//! | tobig | 14    | - | - | - |
+//! | bigplus | 15  | - | - | - |
+//! | bigminus | 16 | - | - | - |
+//! | bigmult | 17  | - | - | - |
+//! | bigdiv | 18   | - | - | - |
+//! | bigrem | 19   | - | - | - |
+//! | bigcmp | 20   | - | - | - |
+//! | frombig | 21  | type identifier | - | - |
+//! | extended | 255 | extended opcode | payload length | payload |
 
-use std::usize;
- 
+// The default memory layout used by [`miku::MikuVM::new`].
+// Other layouts can be configured at runtime with [`config::VmConfig`].
+// The segments are half-open ranges (`START..END`) that follow each other without gaps.
+
+/// The default memory size.
 pub const MEMORY_SIZE: usize = 1024;
 /// The stack segment is 30% of the full memory size.
 pub const STACK_START: usize = 0;
-pub const STACK_END: usize = (MEMORY_SIZE / 100) * 30;
+pub const STACK_END: usize = MEMORY_SIZE * 3 / 10;
 /// The .data segment is 30% of the full memory size.
-pub const DATA_START: usize = STACK_END + 1;
-pub const DATA_END: usize = DATA_START + (MEMORY_SIZE / 100) * 30;
-/// The heap segment is 40% of the full memory size.
-pub const HEAP_START: usize = DATA_END + 1;
+pub const DATA_START: usize = STACK_END;
+pub const DATA_END: usize = DATA_START + MEMORY_SIZE * 3 / 10;
+/// The heap segment is the rest of the memory.
+pub const HEAP_START: usize = DATA_END;
 pub const HEAP_END: usize = MEMORY_SIZE;
 
+pub mod bigint;
+pub mod config;
+pub mod debug;
 pub mod error;
+pub mod gas;
+pub mod heap;
 pub mod tools;
+pub mod trap;
 pub mod inst;
+pub mod interrupt;
+pub mod json;
+pub mod layout;
+pub mod memory;
 pub mod miku;
+pub mod mmio;
+pub mod observer;
+pub mod program;
+pub mod snapshot;
 pub mod types;
 
 #[cfg(test)]
+#[allow(clippy::useless_vec, clippy::excessive_precision, clippy::bool_assert_comparison)]
 mod tests;